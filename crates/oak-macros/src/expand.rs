@@ -0,0 +1,120 @@
+use crate::parse::{Attr, AttrName, Element, Else, If, Node, Root};
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::Ident;
+
+pub fn root(root: &Root) -> TokenStream {
+    match root.nodes.as_slice() {
+        [Node::Element(element)] => self::element(element),
+        [Node::Text(text)] => quote!(::oak::html::text(#text)),
+        [Node::Expr(expr)] => quote!(::std::convert::Into::<::oak::html::Html<_>>::into(#expr)),
+        nodes => {
+            let children = node_list(nodes);
+            quote!(::oak::html::Html::Fragment(#children))
+        }
+    }
+}
+
+/// The name of the vector children are collected into. It can't clash with the names used in
+/// expressions passed to the macro.
+fn children_ident() -> Ident {
+    Ident::new("children", Span::mixed_site())
+}
+
+/// Collects `nodes` into a `Vec<Html<Msg>>`
+fn node_list(nodes: &[Node]) -> TokenStream {
+    let children = children_ident();
+    let pushes = nodes.iter().map(push);
+    quote!({
+        let mut #children: ::std::vec::Vec<::oak::html::Html<_>> = ::std::vec::Vec::new();
+        #(#pushes)*
+        #children
+    })
+}
+
+/// The statements that add `node` to the surrounding children
+fn push(node: &Node) -> TokenStream {
+    let children = children_ident();
+    match node {
+        Node::Element(element) => {
+            let element = self::element(element);
+            quote!(#children.push(#element);)
+        }
+        Node::Text(text) => quote!(#children.push(::oak::html::text(#text));),
+        Node::Expr(expr) => quote!(
+            #children.push(::std::convert::Into::<::oak::html::Html<_>>::into(#expr));
+        ),
+        Node::For(iter) => quote!(
+            #children.extend(
+                ::std::iter::IntoIterator::into_iter(#iter)
+                    .map(::std::convert::Into::<::oak::html::Html<_>>::into),
+            );
+        ),
+        Node::If(condition) => if_(condition),
+        Node::Fragment(nodes) => {
+            let pushes = nodes.iter().map(push);
+            quote!(#(#pushes)*)
+        }
+    }
+}
+
+fn if_(node: &If) -> TokenStream {
+    let condition = &node.condition;
+    let then = node.then.iter().map(push);
+    let otherwise = match &node.otherwise {
+        Some(Else::If(node)) => {
+            let node = if_(node);
+            quote!(else { #node })
+        }
+        Some(Else::Nodes(nodes)) => {
+            let pushes = nodes.iter().map(push);
+            quote!(else { #(#pushes)* })
+        }
+        None => quote!(),
+    };
+    quote!(if #condition { #(#then)* } #otherwise)
+}
+
+fn element(element: &Element) -> TokenStream {
+    // The span of the tag makes an unknown element an error right there
+    let function = Ident::new(&element.name.to_string(), element.name.span());
+    let attrs = element.attrs.iter().map(attr);
+    let attrs = quote!({
+        let attrs: ::std::vec::Vec<::oak::html::Attribute<_>> = ::std::vec![#(#attrs),*];
+        attrs
+    });
+    if element.is_void() {
+        quote!(::oak::html::#function(#attrs))
+    } else {
+        let children = node_list(&element.children);
+        quote!(::oak::html::#function(#attrs, #children))
+    }
+}
+
+fn attr(attr: &Attr) -> TokenStream {
+    match &attr.name {
+        AttrName::Ident(name) => {
+            let (module, function) = match name.to_string().as_str() {
+                "type" => ("attributes", "type_".to_owned()),
+                "for" => ("attributes", "for_".to_owned()),
+                name if name.starts_with("on") => ("events", name.to_owned()),
+                name => ("attributes", name.to_owned()),
+            };
+            let module = Ident::new(module, name.span());
+            let function = Ident::new(&function, name.span());
+            match &attr.value {
+                Some(value) => {
+                    quote_spanned!(name.span()=> ::oak::html::#module::#function(#value))
+                }
+                None => quote_spanned!(name.span()=> ::oak::html::#module::#function(true)),
+            }
+        }
+        AttrName::Hyphenated(name, span) => {
+            let value = match &attr.value {
+                Some(value) => quote!(::std::string::ToString::to_string(&#value)),
+                None => quote!(::std::string::String::new()),
+            };
+            quote_spanned!(*span=> ::oak::html::Attribute::Text(#name.to_owned(), #value))
+        }
+    }
+}
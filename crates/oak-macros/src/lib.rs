@@ -0,0 +1,38 @@
+//! The `html!` macro, re-exported as `oak::html!`.
+
+extern crate proc_macro;
+
+mod expand;
+mod parse;
+mod validate;
+
+use proc_macro::TokenStream;
+use syn::parse_macro_input;
+
+/// Writes a view with JSX-like syntax. It expands to calls of the functions in `oak::html`,
+/// so `html! { <button class="add" on_click={Msg::Increment}>"+"</button> }` is the same as
+/// `button([class("add"), on_click(Msg::Increment)], ["+"])`.
+///
+/// - Attributes are the functions of `oak::html::attributes`, or of `oak::html::events`
+///   when they start with `on`. `type` and `for` stand for `type_` and `for_`, an attribute
+///   without a value is passed `true`, and hyphenated ones like `data-id` are plain text.
+/// - Values are string literals or `{expressions}`.
+/// - Children are elements, string literals, `{expressions}` of anything that turns into
+///   `Html`, `{for iterator}`, `if condition { ... } else { ... }` and fragments `<>...</>`.
+///
+/// The view is checked against HTML at compile time. Unknown elements, attributes on
+/// elements they don't belong to, closing tags on void elements and elements in places HTML
+/// does not allow them, like `<li>` outside of a list or `<button>` in an `<a>`, are errors.
+///
+/// The macro evaluates to an `Html<Msg>`. Several nodes, or anything else that can produce
+/// any number of nodes, like a loop or a condition, become an `Html::Fragment`.
+#[proc_macro]
+pub fn html(input: TokenStream) -> TokenStream {
+    let root = parse_macro_input!(input as parse::Root);
+    if let Err(errors) = validate::root(&root) {
+        // Every error is its own `compile_error!`, and a block lets them all be reported
+        let errors = errors.to_compile_error();
+        return quote::quote!({ #errors }).into();
+    }
+    expand::root(&root).into()
+}
@@ -0,0 +1,255 @@
+use proc_macro2::Span;
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{braced, Error, Expr, Ident, LitStr, Token};
+
+/// Element names that are void, that is they have no children and no closing tag
+pub const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Everything inside of `html! { ... }`
+pub struct Root {
+    pub nodes: Vec<Node>,
+}
+
+pub enum Node {
+    Element(Element),
+    Text(LitStr),
+    /// `{expression}`
+    Expr(Expr),
+    /// `{for iterator}`
+    For(Expr),
+    If(If),
+    /// `<>...</>`
+    Fragment(Vec<Node>),
+}
+
+pub struct Element {
+    pub name: Ident,
+    pub attrs: Vec<Attr>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    pub fn is_void(&self) -> bool {
+        VOID_ELEMENTS.contains(&self.name.to_string().as_str())
+    }
+}
+
+pub struct Attr {
+    pub name: AttrName,
+    pub value: Option<Expr>,
+}
+
+pub enum AttrName {
+    /// An attribute with a function in `oak::html`, like `class` or `on_click`
+    Ident(Ident),
+    /// An attribute like `data-id` or `aria-label`
+    Hyphenated(String, Span),
+}
+
+pub struct If {
+    pub condition: Expr,
+    pub then: Vec<Node>,
+    pub otherwise: Option<Else>,
+}
+
+pub enum Else {
+    If(Box<If>),
+    Nodes(Vec<Node>),
+}
+
+impl Parse for Root {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let nodes = parse_nodes(input)?;
+        if nodes.is_empty() {
+            return Err(input.error("expected at least one node"));
+        }
+        if !input.is_empty() {
+            return Err(input.error("unexpected closing tag"));
+        }
+        Ok(Root { nodes })
+    }
+}
+
+/// Parses nodes up to the end of the input or a closing tag
+fn parse_nodes(input: ParseStream) -> Result<Vec<Node>> {
+    let mut nodes = vec![];
+    while !input.is_empty() && !at_closing_tag(input) {
+        nodes.push(input.parse()?);
+    }
+    Ok(nodes)
+}
+
+fn at_closing_tag(input: ParseStream) -> bool {
+    input.peek(Token![<]) && input.peek2(Token![/])
+}
+
+impl Parse for Node {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Token![<]) && input.peek2(Token![>]) {
+            input.parse::<Token![<]>()?;
+            input.parse::<Token![>]>()?;
+            let children = parse_nodes(input)?;
+            if input.is_empty() {
+                return Err(input.error("expected `</>` to close the fragment"));
+            }
+            input.parse::<Token![<]>()?;
+            input.parse::<Token![/]>()?;
+            input.parse::<Token![>]>().map_err(|error| {
+                Error::new(error.span(), "expected `</>` to close the fragment")
+            })?;
+            Ok(Node::Fragment(children))
+        } else if input.peek(Token![<]) {
+            Ok(Node::Element(input.parse()?))
+        } else if input.peek(LitStr) {
+            Ok(Node::Text(input.parse()?))
+        } else if input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            if content.peek(Token![for]) {
+                content.parse::<Token![for]>()?;
+                Ok(Node::For(content.parse()?))
+            } else {
+                Ok(Node::Expr(content.parse()?))
+            }
+        } else if input.peek(Token![if]) {
+            Ok(Node::If(input.parse()?))
+        } else {
+            Err(input.error("expected an element, text in quotes, `{...}` or `if`"))
+        }
+    }
+}
+
+impl Parse for Element {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![<]>()?;
+        let name = Ident::parse_any(input)?;
+
+        let mut attrs = vec![];
+        while !input.peek(Token![>]) && !input.peek(Token![/]) {
+            if input.is_empty() {
+                return Err(Error::new(name.span(), format!("unclosed `<{}>`", name)));
+            }
+            attrs.push(input.parse()?);
+        }
+
+        let mut element = Element {
+            name,
+            attrs,
+            children: vec![],
+        };
+        if input.peek(Token![/]) {
+            input.parse::<Token![/]>()?;
+            input.parse::<Token![>]>()?;
+            return Ok(element);
+        }
+        input.parse::<Token![>]>()?;
+        // Void elements can't be closed, so `<br>` is complete on its own
+        if element.is_void() {
+            if at_closing_tag(input) {
+                let fork = input.fork();
+                fork.parse::<Token![<]>()?;
+                fork.parse::<Token![/]>()?;
+                if Ident::parse_any(&fork)? == element.name {
+                    return Err(Error::new(
+                        element.name.span(),
+                        format!(
+                            "`<{}>` is a void element, so it has no children or closing tag",
+                            element.name
+                        ),
+                    ));
+                }
+            }
+            return Ok(element);
+        }
+
+        element.children = parse_nodes(input)?;
+        if input.is_empty() {
+            return Err(Error::new(
+                element.name.span(),
+                format!("expected `</{}>` to close this element", element.name),
+            ));
+        }
+        input.parse::<Token![<]>()?;
+        input.parse::<Token![/]>()?;
+        let closing = Ident::parse_any(input)?;
+        if closing != element.name {
+            return Err(Error::new(
+                closing.span(),
+                format!("expected `</{}>`, found `</{}>`", element.name, closing),
+            ));
+        }
+        input.parse::<Token![>]>()?;
+        Ok(element)
+    }
+}
+
+impl Parse for Attr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let first = Ident::parse_any(input)?;
+        let name = if input.peek(Token![-]) {
+            let mut name = first.to_string();
+            while input.peek(Token![-]) {
+                input.parse::<Token![-]>()?;
+                name.push('-');
+                name.push_str(&Ident::parse_any(input)?.to_string());
+            }
+            AttrName::Hyphenated(name, first.span())
+        } else {
+            AttrName::Ident(first)
+        };
+
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            if input.peek(syn::token::Brace) {
+                let content;
+                braced!(content in input);
+                Some(content.parse()?)
+            } else if input.peek(syn::Lit) {
+                Some(Expr::Lit(input.parse()?))
+            } else {
+                return Err(input.error("expected a literal or `{...}` as the attribute value"));
+            }
+        } else {
+            None
+        };
+        Ok(Attr { name, value })
+    }
+}
+
+impl Parse for If {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![if]>()?;
+        let condition = Expr::parse_without_eager_brace(input)?;
+        let then = parse_block(input)?;
+        let otherwise = if input.peek(Token![else]) {
+            input.parse::<Token![else]>()?;
+            if input.peek(Token![if]) {
+                Some(Else::If(Box::new(input.parse()?)))
+            } else {
+                Some(Else::Nodes(parse_block(input)?))
+            }
+        } else {
+            None
+        };
+        Ok(If {
+            condition,
+            then,
+            otherwise,
+        })
+    }
+}
+
+/// Parses the nodes of `{ ... }`
+fn parse_block(input: ParseStream) -> Result<Vec<Node>> {
+    let content;
+    braced!(content in input);
+    let nodes = parse_nodes(&content)?;
+    if !content.is_empty() {
+        return Err(content.error("unexpected closing tag"));
+    }
+    Ok(nodes)
+}
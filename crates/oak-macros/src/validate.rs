@@ -0,0 +1,372 @@
+//! Checks a view against the rules of HTML, so mistakes like `href` on a `div` or a `tr`
+//! directly in a `table` are reported at compile time.
+
+use crate::parse::{Attr, AttrName, Else, Node, Root};
+use syn::{Error, Expr, Lit};
+
+/// Every element there is a function for in `oak::html`
+const ELEMENTS: &[&str] = &[
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "div",
+    "span",
+    "p",
+    "pre",
+    "blockquote",
+    "a",
+    "code",
+    "em",
+    "strong",
+    "i",
+    "b",
+    "u",
+    "sub",
+    "sup",
+    "ol",
+    "ul",
+    "li",
+    "dl",
+    "dt",
+    "dd",
+    "form",
+    "textarea",
+    "button",
+    "select",
+    "option",
+    "section",
+    "nav",
+    "article",
+    "aside",
+    "header",
+    "footer",
+    "address",
+    "main",
+    "figure",
+    "figcaption",
+    "table",
+    "caption",
+    "colgroup",
+    "tbody",
+    "thead",
+    "tfoot",
+    "tr",
+    "td",
+    "th",
+    "fieldset",
+    "legend",
+    "label",
+    "datalist",
+    "optgroup",
+    "output",
+    "progress",
+    "meter",
+    "audio",
+    "video",
+    "object",
+    "ins",
+    "del",
+    "small",
+    "cite",
+    "dfn",
+    "abbr",
+    "time",
+    "var",
+    "samp",
+    "kbd",
+    "s",
+    "q",
+    "mark",
+    "ruby",
+    "rt",
+    "rp",
+    "bdi",
+    "bdo",
+    "details",
+    "summary",
+    "menuitem",
+    "menu",
+    "iframe",
+    "canvas",
+    "link",
+    "meta",
+    "hr",
+    "br",
+    "input",
+    "img",
+    "col",
+    "source",
+    "track",
+    "embed",
+    "param",
+    "wbr",
+    "area",
+    "base",
+];
+
+/// Attributes that only belong on some elements. Attributes that are not listed here, like
+/// `class`, `id` or event handlers, go on any element.
+const ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("href", &["a", "area", "base", "link"]),
+    (
+        "src",
+        &[
+            "audio", "embed", "iframe", "img", "input", "script", "source", "track", "video",
+        ],
+    ),
+    ("placeholder", &["input", "textarea"]),
+    (
+        "name",
+        &[
+            "button", "details", "fieldset", "form", "iframe", "input", "map", "meta", "object",
+            "output", "param", "select", "slot", "textarea",
+        ],
+    ),
+    (
+        "type",
+        &[
+            "a", "button", "embed", "input", "link", "menu", "object", "ol", "script", "source",
+            "style",
+        ],
+    ),
+    ("for", &["label", "output"]),
+    ("required", &["input", "select", "textarea"]),
+    (
+        "value",
+        &[
+            "button", "data", "input", "li", "meter", "option", "output", "param", "progress",
+            "select", "textarea",
+        ],
+    ),
+    ("checked", &["input"]),
+    ("indeterminate", &["input"]),
+    ("selected", &["option"]),
+    (
+        "disabled",
+        &[
+            "button", "fieldset", "input", "link", "optgroup", "option", "select", "textarea",
+        ],
+    ),
+    ("multiple", &["input", "select"]),
+    ("readonly", &["input", "textarea"]),
+];
+
+/// Elements that can only be direct children of certain elements
+const PARENTS: &[(&str, &[&str])] = &[
+    ("li", &["ul", "ol", "menu"]),
+    ("dt", &["dl", "div"]),
+    ("dd", &["dl", "div"]),
+    ("option", &["select", "datalist", "optgroup"]),
+    ("optgroup", &["select"]),
+    ("tr", &["thead", "tbody", "tfoot"]),
+    ("td", &["tr"]),
+    ("th", &["tr"]),
+    ("thead", &["table"]),
+    ("tbody", &["table"]),
+    ("tfoot", &["table"]),
+    ("caption", &["table"]),
+    ("colgroup", &["table"]),
+    ("col", &["colgroup"]),
+    ("figcaption", &["figure"]),
+    ("legend", &["fieldset"]),
+    ("summary", &["details"]),
+    ("rt", &["ruby"]),
+    ("rp", &["ruby"]),
+    ("source", &["audio", "video"]),
+    ("track", &["audio", "video"]),
+    ("param", &["object"]),
+];
+
+/// Elements whose content can only be phrasing content, that is text and inline elements
+const PHRASING_PARENTS: &[&str] = &[
+    "p", "h1", "h2", "h3", "h4", "h5", "h6", "pre", "span", "em", "strong", "code", "i", "b", "u",
+    "sub", "sup", "small", "cite", "dfn", "abbr", "time", "var", "samp", "kbd", "s", "q", "mark",
+    "bdi", "bdo", "label", "legend", "button", "output", "rt", "rp",
+];
+
+const PHRASING: &[&str] = &[
+    "a", "abbr", "audio", "b", "bdi", "bdo", "br", "button", "canvas", "cite", "code", "datalist",
+    "del", "dfn", "em", "embed", "i", "iframe", "img", "input", "ins", "kbd", "label", "link",
+    "mark", "meta", "meter", "object", "output", "progress", "q", "ruby", "s", "samp", "select",
+    "small", "span", "strong", "sub", "sup", "textarea", "time", "u", "var", "video", "wbr",
+];
+
+/// Interactive content, which can't be nested in elements like `a` or `button`
+const INTERACTIVE: &[&str] = &[
+    "a", "button", "details", "embed", "iframe", "input", "label", "select", "textarea",
+];
+
+/// Elements that can't contain interactive content
+const CONTAINERS: &[&str] = &["a", "button", "label"];
+
+pub fn root(root: &Root) -> Result<(), Error> {
+    let mut validator = Validator { errors: None };
+    validator.nodes(&root.nodes, None, &[]);
+    match validator.errors {
+        Some(errors) => Err(errors),
+        None => Ok(()),
+    }
+}
+
+struct Validator {
+    errors: Option<Error>,
+}
+
+impl Validator {
+    fn error(&mut self, error: Error) {
+        match &mut self.errors {
+            Some(errors) => errors.combine(error),
+            None => self.errors = Some(error),
+        }
+    }
+
+    /// Validates `nodes`, which are children of `parent`, if it is part of the macro input.
+    /// `ancestors` are the elements the nodes are in that restrict interactive content.
+    fn nodes(&mut self, nodes: &[Node], parent: Option<&str>, ancestors: &[String]) {
+        for node in nodes {
+            match node {
+                Node::Element(element) => {
+                    let name = element.name.to_string();
+                    let span = element.name.span();
+                    if !ELEMENTS.contains(&name.as_str()) {
+                        self.error(Error::new(span, format!("unknown element `<{}>`", name)));
+                        continue;
+                    }
+
+                    for attr in &element.attrs {
+                        self.attr(&name, attr);
+                    }
+
+                    if let Some((_, parents)) = PARENTS.iter().find(|(child, _)| *child == name) {
+                        if let Some(parent) = parent {
+                            if !parents.contains(&parent) {
+                                self.error(Error::new(span, misplaced(&name, parent, parents)));
+                            }
+                        }
+                    }
+                    if let Some(parent) = parent {
+                        if PHRASING_PARENTS.contains(&parent) && !PHRASING.contains(&name.as_str())
+                        {
+                            self.error(Error::new(
+                                span,
+                                format!(
+                                    "`<{}>` can't be inside `<{}>`, which only takes text and \
+                                     inline elements",
+                                    name, parent
+                                ),
+                            ));
+                        }
+                    }
+
+                    let interactive = is_interactive(&name, &element.attrs);
+                    let container = ancestors.iter().find(|ancestor| {
+                        // A label only can't contain other labels, as it labels what's inside
+                        if *ancestor == "label" {
+                            name == "label"
+                        } else {
+                            interactive
+                        }
+                    });
+                    if let Some(container) = container {
+                        self.error(Error::new(
+                            span,
+                            format!(
+                                "`<{}>` can't be inside `<{}>`, as both are interactive",
+                                name, container
+                            ),
+                        ));
+                    }
+                    let mut ancestors = ancestors.to_vec();
+                    if CONTAINERS.contains(&name.as_str()) {
+                        ancestors.push(name.clone());
+                    }
+                    self.nodes(&element.children, Some(&name), &ancestors);
+                }
+                // Text is phrasing content, and what expressions produce is unknown
+                Node::Text(_) | Node::Expr(_) | Node::For(_) => {}
+                Node::If(node) => {
+                    let mut node = node;
+                    loop {
+                        self.nodes(&node.then, parent, ancestors);
+                        match &node.otherwise {
+                            Some(Else::If(otherwise)) => node = otherwise,
+                            Some(Else::Nodes(nodes)) => {
+                                self.nodes(nodes, parent, ancestors);
+                                break;
+                            }
+                            None => break,
+                        }
+                    }
+                }
+                Node::Fragment(nodes) => self.nodes(nodes, parent, ancestors),
+            }
+        }
+    }
+
+    fn attr(&mut self, element: &str, attr: &Attr) {
+        let name = match &attr.name {
+            AttrName::Ident(name) => name,
+            AttrName::Hyphenated(..) => return,
+        };
+        let attr_name = name.to_string();
+        let attr_name = attr_name.trim_end_matches('_');
+        if let Some((_, elements)) = ATTRIBUTES.iter().find(|(attr, _)| *attr == attr_name) {
+            if !elements.contains(&element) {
+                self.error(Error::new(
+                    name.span(),
+                    format!(
+                        "`{}` is not an attribute of `<{}>`, only of {}",
+                        attr_name,
+                        element,
+                        list(elements)
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Whether the element is interactive content. Hidden inputs are not.
+fn is_interactive(name: &str, attrs: &[Attr]) -> bool {
+    if name == "input" {
+        let hidden = attrs.iter().any(|attr| match (&attr.name, &attr.value) {
+            (AttrName::Ident(attr_name), Some(Expr::Lit(value))) if attr_name == "type" => {
+                match &value.lit {
+                    Lit::Str(value) => value.value() == "hidden",
+                    _ => false,
+                }
+            }
+            _ => false,
+        });
+        return !hidden;
+    }
+    INTERACTIVE.contains(&name)
+}
+
+fn misplaced(name: &str, parent: &str, parents: &[&str]) -> String {
+    if name == "tr" && parent == "table" {
+        "`<tr>` can't be directly inside `<table>`, put it in a `<tbody>`".to_owned()
+    } else {
+        format!(
+            "`<{}>` can't be inside `<{}>`, only inside {}",
+            name,
+            parent,
+            list(parents)
+        )
+    }
+}
+
+/// Formats elements like "`<ul>`, `<ol>` or `<menu>`"
+fn list(elements: &[&str]) -> String {
+    let elements = elements
+        .iter()
+        .map(|element| format!("`<{}>`", element))
+        .collect::<Vec<_>>();
+    match elements.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => elements.join(""),
+    }
+}
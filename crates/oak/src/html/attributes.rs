@@ -1,4 +1,4 @@
-use crate::html::Attribute;
+use crate::html::{Attribute, Value};
 
 macro_rules! declare_text_attributes {
     ($($x:ident, $tag:expr)*) => ($(
@@ -14,8 +14,8 @@ macro_rules! declare_text_attributes {
 
 macro_rules! declare_bool_attributes {
     ($($x:ident, $tag:expr)*) => ($(
-        pub fn $x<Msg>() -> Attribute<Msg> {
-            Attribute::Bool($tag.to_owned())
+        pub fn $x<Msg>(value: bool) -> Attribute<Msg> {
+            Attribute::Bool($tag.to_owned(), value)
         }
     )*);
 
@@ -24,6 +24,30 @@ macro_rules! declare_bool_attributes {
     )*);
 }
 
+macro_rules! declare_text_properties {
+    ($($x:ident, $property:expr)*) => ($(
+        pub fn $x<Msg>(value: &str) -> Attribute<Msg> {
+            Attribute::Property($property.to_owned(), Value::String(value.to_owned()))
+        }
+    )*);
+
+    ($($x:ident)*) => ($(
+        declare_text_properties!($x, stringify!($x));
+    )*);
+}
+
+macro_rules! declare_bool_properties {
+    ($($x:ident, $property:expr)*) => ($(
+        pub fn $x<Msg>(value: bool) -> Attribute<Msg> {
+            Attribute::Property($property.to_owned(), Value::Bool(value))
+        }
+    )*);
+
+    ($($x:ident)*) => ($(
+        declare_bool_properties!($x, stringify!($x));
+    )*);
+}
+
 pub fn class_list<Msg>(classes: &[(&str, bool)]) -> Attribute<Msg> {
     let active = classes
         .iter()
@@ -32,17 +56,43 @@ pub fn class_list<Msg>(classes: &[(&str, bool)]) -> Attribute<Msg> {
         .collect::<Vec<_>>();
 
     // TODO: Change `class` to use Into<Cow> and use it here
-    Attribute::Text("className".to_owned(), active.join(" "))
+    Attribute::Text("class".to_owned(), active.join(" "))
+}
+
+/// Inline styles, like `style(&[("color", "red"), ("margin", "0 auto")])`
+pub fn style<Msg>(properties: &[(&str, &str)]) -> Attribute<Msg> {
+    Attribute::Style(
+        properties
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+            .collect(),
+    )
 }
 
-pub fn key<Msg>(key: String) -> Attribute<Msg> {
-    Attribute::Key(key)
+/// Tells an element apart from its siblings, so that lists are diffed by key instead of by
+/// position. Keys are left out of the markup.
+///
+/// ```
+/// use oak::html::Html;
+///
+/// let items: Html<()> = oak::html! {
+///     <ul>
+///         <li key="first">"First"</li>
+///         <li key={&format!("item-{}", 2)}>"Second"</li>
+///     </ul>
+/// };
+/// assert_eq!(
+///     oak::ssr::render_to_string(&items),
+///     "<ul><li>First</li><li>Second</li></ul>"
+/// );
+/// ```
+pub fn key<Msg>(key: &str) -> Attribute<Msg> {
+    Attribute::Key(key.to_owned())
 }
 
 declare_text_attributes! {
     placeholder
     name
-    value
     id
     href
     class
@@ -56,6 +106,22 @@ declare_text_attributes! {
 
 declare_bool_attributes! {
     autofocus
-    checked
     hidden
+    required
+}
+
+declare_text_properties! {
+    value
+}
+
+declare_bool_properties! {
+    checked
+    selected
+    disabled
+    multiple
+    indeterminate
+}
+
+declare_bool_properties! {
+    readonly, "readOnly"
 }
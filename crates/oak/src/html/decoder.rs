@@ -0,0 +1,355 @@
+//! Decoders read the parts of an event a handler cares about, like `target.value`, `key`,
+//! `clientX` or `detail`, and turn them into a message.
+//!
+//! A decoder only sees the event through the `EventSource` trait, so it can be tested
+//! natively against a `FakeEvent`:
+//!
+//! ```
+//! use oak::html::decoder::{self, FakeEvent};
+//!
+//! let decoder = decoder::float("clientX").map(|x| x as i32 / 10);
+//! let event = FakeEvent::new().with("clientX", 42.0);
+//! assert_eq!(decoder.decode(&event), Ok(4));
+//! ```
+
+pub use crate::html::Value;
+
+use crate::html::events::Key;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+#[cfg(feature = "browser")]
+use wasm_bindgen::JsValue;
+
+/// Anything decoders can read fields from. Implemented for DOM events in the browser.
+pub trait EventSource {
+    /// Follows `path` from the event, so `["target", "value"]` reads `event.target.value`.
+    /// Returns `None` when a field on the way is missing.
+    fn get(&self, path: &[&str]) -> Option<Value>;
+}
+
+#[cfg(feature = "browser")]
+impl EventSource for web_sys::Event {
+    fn get(&self, path: &[&str]) -> Option<Value> {
+        let mut value: JsValue = self.into();
+        for segment in path {
+            if value.is_null() || value.is_undefined() {
+                return None;
+            }
+            value = js_sys::Reflect::get(&value, &JsValue::from_str(segment)).ok()?;
+        }
+        if value.is_undefined() {
+            None
+        } else if value.is_null() {
+            Some(Value::Null)
+        } else if let Some(value) = value.as_bool() {
+            Some(Value::Bool(value))
+        } else if let Some(value) = value.as_f64() {
+            Some(Value::Number(value))
+        } else {
+            value.as_string().map(Value::String)
+        }
+    }
+}
+
+/// An event made of plain values, for testing decoders outside of a browser.
+#[derive(Clone, Debug, Default)]
+pub struct FakeEvent {
+    fields: HashMap<String, Value>,
+}
+
+impl FakeEvent {
+    pub fn new() -> Self {
+        FakeEvent::default()
+    }
+
+    /// Sets the field at a dotted path, like `target.value`
+    pub fn with<V: Into<Value>>(mut self, path: &str, value: V) -> Self {
+        self.fields.insert(path.to_owned(), value.into());
+        self
+    }
+}
+
+impl EventSource for FakeEvent {
+    fn get(&self, path: &[&str]) -> Option<Value> {
+        self.fields.get(&path.join(".")).cloned()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The field at this path does not exist
+    Missing(String),
+    /// The field at this path is not of the expected type
+    WrongType {
+        path: String,
+        expected: &'static str,
+    },
+    Failure(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Missing(path) => write!(f, "expected a field at `{}`", path),
+            Error::WrongType { path, expected } => {
+                write!(f, "expected `{}` to be a {}", path, expected)
+            }
+            Error::Failure(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+type Decode<T> = dyn Fn(&dyn EventSource) -> Result<T, Error>;
+
+pub struct Decoder<T>(Rc<Decode<T>>);
+
+impl<T: 'static> Decoder<T> {
+    pub fn new<F>(decode: F) -> Self
+    where
+        F: Fn(&dyn EventSource) -> Result<T, Error> + 'static,
+    {
+        Decoder(Rc::new(decode))
+    }
+
+    pub fn decode(&self, event: &dyn EventSource) -> Result<T, Error> {
+        (self.0)(event)
+    }
+
+    pub fn map<U: 'static, F>(self, f: F) -> Decoder<U>
+    where
+        F: Fn(T) -> U + 'static,
+    {
+        Decoder::new(move |event| self.decode(event).map(&f))
+    }
+
+    /// Decodes with the decoder returned by `f`, which can depend on what was decoded so far
+    pub fn and_then<U: 'static, F>(self, f: F) -> Decoder<U>
+    where
+        F: Fn(T) -> Decoder<U> + 'static,
+    {
+        Decoder::new(move |event| f(self.decode(event)?).decode(event))
+    }
+
+    /// Fails unless `predicate` holds for the decoded value
+    pub fn filter<F>(self, predicate: F) -> Decoder<T>
+    where
+        F: Fn(&T) -> bool + 'static,
+    {
+        Decoder::new(move |event| {
+            let value = self.decode(event)?;
+            if predicate(&value) {
+                Ok(value)
+            } else {
+                Err(Error::Failure("the value was filtered out".to_owned()))
+            }
+        })
+    }
+}
+
+impl<T> Clone for Decoder<T> {
+    fn clone(&self) -> Self {
+        Decoder(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for Decoder<T> {
+    /// Decoders are closures, so two decoders are only equal when they are the same one
+    fn eq(&self, other: &Decoder<T>) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> fmt::Debug for Decoder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Decoder")
+    }
+}
+
+fn field(event: &dyn EventSource, path: &str) -> Result<Value, Error> {
+    let segments = path.split('.').collect::<Vec<_>>();
+    event
+        .get(&segments)
+        .ok_or_else(|| Error::Missing(path.to_owned()))
+}
+
+fn wrong_type(path: &str, expected: &'static str) -> Error {
+    Error::WrongType {
+        path: path.to_owned(),
+        expected,
+    }
+}
+
+/// Reads the raw value at a dotted path
+pub fn value(path: &str) -> Decoder<Value> {
+    let path = path.to_owned();
+    Decoder::new(move |event| field(event, &path))
+}
+
+pub fn string(path: &str) -> Decoder<String> {
+    let path = path.to_owned();
+    Decoder::new(move |event| match field(event, &path)? {
+        Value::String(value) => Ok(value),
+        _ => Err(wrong_type(&path, "string")),
+    })
+}
+
+pub fn float(path: &str) -> Decoder<f64> {
+    let path = path.to_owned();
+    Decoder::new(move |event| match field(event, &path)? {
+        Value::Number(value) => Ok(value),
+        _ => Err(wrong_type(&path, "number")),
+    })
+}
+
+pub fn int(path: &str) -> Decoder<i64> {
+    let path = path.to_owned();
+    Decoder::new(move |event| match field(event, &path)? {
+        Value::Number(value) if value.fract() == 0.0 => Ok(value as i64),
+        _ => Err(wrong_type(&path, "integer")),
+    })
+}
+
+pub fn bool(path: &str) -> Decoder<bool> {
+    let path = path.to_owned();
+    Decoder::new(move |event| match field(event, &path)? {
+        Value::Bool(value) => Ok(value),
+        _ => Err(wrong_type(&path, "bool")),
+    })
+}
+
+/// Always decodes to `value`
+pub fn succeed<T: Clone + 'static>(value: T) -> Decoder<T> {
+    Decoder::new(move |_| Ok(value.clone()))
+}
+
+/// Never decodes, so no message is sent
+pub fn fail<T: 'static>(message: &str) -> Decoder<T> {
+    let message = message.to_owned();
+    Decoder::new(move |_| Err(Error::Failure(message.clone())))
+}
+
+pub fn map2<A, B, T, F>(a: Decoder<A>, b: Decoder<B>, f: F) -> Decoder<T>
+where
+    A: 'static,
+    B: 'static,
+    T: 'static,
+    F: Fn(A, B) -> T + 'static,
+{
+    Decoder::new(move |event| Ok(f(a.decode(event)?, b.decode(event)?)))
+}
+
+/// Uses the first decoder that succeeds
+pub fn one_of<T: 'static>(decoders: Vec<Decoder<T>>) -> Decoder<T> {
+    Decoder::new(move |event| {
+        let mut errors = vec![];
+        for decoder in &decoders {
+            match decoder.decode(event) {
+                Ok(value) => return Ok(value),
+                Err(error) => errors.push(error.to_string()),
+            }
+        }
+        Err(Error::Failure(format!(
+            "none of the decoders succeeded: {}",
+            errors.join(", ")
+        )))
+    })
+}
+
+/// `event.target.value`, as used by inputs, selects and text areas
+pub fn target_value() -> Decoder<String> {
+    string("target.value")
+}
+
+/// `event.target.checked`, as used by checkboxes and radio buttons
+pub fn target_checked() -> Decoder<bool> {
+    bool("target.checked")
+}
+
+/// The `key` of a keyboard event
+pub fn key() -> Decoder<Key> {
+    string("key").map(|key| Key::from(key.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_fields_at_dotted_paths() {
+        let event = FakeEvent::new()
+            .with("target.value", "hello")
+            .with("target.checked", true)
+            .with("detail", 3);
+        assert_eq!(target_value().decode(&event), Ok("hello".to_owned()));
+        assert_eq!(target_checked().decode(&event), Ok(true));
+        assert_eq!(int("detail").decode(&event), Ok(3));
+        assert_eq!(value("detail").decode(&event), Ok(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn decodes_keys() {
+        let event = FakeEvent::new().with("key", "Enter");
+        assert_eq!(key().decode(&event), Ok(Key::Enter));
+        let event = FakeEvent::new().with("key", "a");
+        assert_eq!(key().decode(&event), Ok(Key::Character("a".to_owned())));
+    }
+
+    #[test]
+    fn maps_and_combines_decoded_values() {
+        let event = FakeEvent::new().with("clientX", 12.0).with("clientY", 34.0);
+        let position = map2(float("clientX"), float("clientY"), |x, y| (x, y));
+        assert_eq!(position.decode(&event), Ok((12.0, 34.0)));
+        let doubled = int("clientX").map(|x| x * 2);
+        assert_eq!(doubled.decode(&event), Ok(24));
+        let quadrant = float("clientX").and_then(|x| {
+            if x > 10.0 {
+                succeed("right")
+            } else {
+                succeed("left")
+            }
+        });
+        assert_eq!(quadrant.decode(&event), Ok("right"));
+    }
+
+    #[test]
+    fn reports_why_decoding_failed() {
+        let event = FakeEvent::new()
+            .with("target.value", "hello")
+            .with("detail", 1.5);
+        assert_eq!(
+            string("target.name").decode(&event),
+            Err(Error::Missing("target.name".to_owned()))
+        );
+        assert_eq!(
+            bool("target.value").decode(&event),
+            Err(Error::WrongType {
+                path: "target.value".to_owned(),
+                expected: "bool",
+            })
+        );
+        assert_eq!(
+            int("detail").decode(&event),
+            Err(Error::WrongType {
+                path: "detail".to_owned(),
+                expected: "integer",
+            })
+        );
+        assert_eq!(
+            fail::<()>("nope").decode(&event),
+            Err(Error::Failure("nope".to_owned()))
+        );
+        assert!(target_value()
+            .filter(|value| value.is_empty())
+            .decode(&event)
+            .is_err());
+    }
+
+    #[test]
+    fn one_of_uses_the_first_decoder_that_succeeds() {
+        let decoder = one_of(vec![int("detail"), int("which")]);
+        let event = FakeEvent::new().with("which", 13);
+        assert_eq!(decoder.decode(&event), Ok(13));
+        assert!(decoder.decode(&FakeEvent::new()).is_err());
+    }
+}
@@ -8,6 +8,7 @@ macro_rules! declare_elements {
         ) -> Html<Msg> {
             Html::Element(Element {
                 name: stringify!($x).to_owned(),
+                namespace: None,
                 children: Children::Nodes(children.as_ref().to_vec().into_iter().map(|c| c.into()).collect()),
                 attrs: attrs.as_ref().to_vec(),
             })
@@ -20,6 +21,7 @@ macro_rules! declare_void_elements {
         pub fn $x<Msg: Clone, A: AsRef<[Attribute<Msg>]>>(attrs: A) -> Html<Msg> {
             Html::Element(Element {
                 name: stringify!($x).to_owned(),
+                namespace: None,
                 children: Children::SelfClosing,
                 attrs: attrs.as_ref().to_vec(),
             })
@@ -72,7 +74,6 @@ declare_elements!(
     table
     caption
     colgroup
-    col
     tbody
     thead
     tfoot
@@ -89,11 +90,7 @@ declare_elements!(
     meter
     audio
     video
-    source
-    track
-    embed
     object
-    param
     ins
     del
     small
@@ -112,20 +109,27 @@ declare_elements!(
     rp
     bdi
     bdo
-    wbr
     details
     summary
     menuitem
     menu
+    iframe
+    canvas
 );
 
 declare_void_elements! {
-    link
-    meta
-    hr
+    area
+    base
     br
-    input
-    iframe
-    canvas
+    col
+    embed
+    hr
     img
+    input
+    link
+    meta
+    param
+    source
+    track
+    wbr
 }
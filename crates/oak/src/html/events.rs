@@ -1,43 +1,232 @@
-use crate::html::{Attribute, EventListener, EventToMessage};
+use crate::html::{decoder, decoder::Decoder, Attribute, EventListener, EventToMessage};
 use std::default::Default;
 
-pub fn on_click<Msg: Clone + 'static>(message: Msg) -> Attribute<Msg> {
+macro_rules! declare_events {
+    ($($x:ident, $type_:expr)*) => ($(
+        pub fn $x<Msg: Clone + 'static>(message: Msg) -> Attribute<Msg> {
+            Attribute::Event(EventListener {
+                type_: $type_.to_owned(),
+                to_message: EventToMessage::StaticMsg(message),
+                stop_propagation: false,
+                prevent_default: false,
+                sync: false,
+                js_closure: Default::default(),
+            })
+        }
+    )*)
+}
+
+macro_rules! declare_key_events {
+    ($($x:ident, $type_:expr)*) => ($(
+        pub fn $x<Msg: 'static>(to_message: fn(Key) -> Msg) -> Attribute<Msg> {
+            Attribute::Event(EventListener {
+                type_: $type_.to_owned(),
+                to_message: EventToMessage::Key(to_message),
+                stop_propagation: false,
+                prevent_default: false,
+                sync: false,
+                js_closure: Default::default(),
+            })
+        }
+    )*)
+}
+
+declare_events! {
+    on_click, "click"
+    on_double_click, "dblclick"
+    on_mouse_down, "mousedown"
+    on_mouse_up, "mouseup"
+    on_mouse_enter, "mouseenter"
+    on_mouse_leave, "mouseleave"
+    on_mouse_over, "mouseover"
+    on_mouse_out, "mouseout"
+    on_focus, "focus"
+    on_blur, "blur"
+    on_focus_in, "focusin"
+    on_focus_out, "focusout"
+    on_reset, "reset"
+}
+
+declare_key_events! {
+    on_key_down, "keydown"
+    on_key_up, "keyup"
+    on_key_press, "keypress"
+}
+
+/// Sends the value of the input on every change. Inputs render synchronously so that the
+/// value never lags behind what was typed.
+pub fn on_input<Msg: 'static>(to_message: fn(String) -> Msg) -> Attribute<Msg> {
+    Attribute::Event(EventListener {
+        type_: "input".to_owned(),
+        to_message: EventToMessage::Input(to_message),
+        stop_propagation: true,
+        prevent_default: false,
+        sync: true,
+        js_closure: Default::default(),
+    })
+}
+
+/// Sends the value of an input, select or text area once a change is committed, like when
+/// it loses focus or an option is picked.
+pub fn on_change<Msg: 'static>(to_message: fn(String) -> Msg) -> Attribute<Msg> {
+    Attribute::Event(EventListener {
+        type_: "change".to_owned(),
+        to_message: EventToMessage::Input(to_message),
+        stop_propagation: true,
+        prevent_default: false,
+        sync: true,
+        js_closure: Default::default(),
+    })
+}
+
+/// Sends the position of the mouse relative to the viewport, as `clientX` and `clientY`,
+/// whenever it moves.
+pub fn on_mouse_move<Msg: 'static>(to_message: fn(f64, f64) -> Msg) -> Attribute<Msg> {
+    on(
+        "mousemove",
+        decoder::map2(
+            decoder::float("clientX"),
+            decoder::float("clientY"),
+            to_message,
+        ),
+    )
+}
+
+/// Sends whether a checkbox or radio button is checked whenever it changes.
+pub fn on_check<Msg: 'static>(to_message: fn(bool) -> Msg) -> Attribute<Msg> {
+    Attribute::Event(EventListener {
+        type_: "change".to_owned(),
+        to_message: EventToMessage::Check(to_message),
+        stop_propagation: true,
+        prevent_default: false,
+        sync: true,
+        js_closure: Default::default(),
+    })
+}
+
+/// Sends `message` when a form is submitted, without the browser reloading the page.
+pub fn on_submit<Msg: Clone + 'static>(message: Msg) -> Attribute<Msg> {
     Attribute::Event(EventListener {
-        type_: "click".to_owned(),
+        type_: "submit".to_owned(),
         to_message: EventToMessage::StaticMsg(message),
         stop_propagation: false,
+        prevent_default: true,
+        sync: false,
+        js_closure: Default::default(),
+    })
+}
+
+/// Sends the result of `to_message`, which gets the raw DOM event, unless it returns `None`.
+#[cfg(feature = "browser")]
+pub fn on_event<Msg: 'static>(
+    type_: &str,
+    to_message: fn(&web_sys::Event) -> Option<Msg>,
+) -> Attribute<Msg> {
+    Attribute::Event(EventListener {
+        type_: type_.to_owned(),
+        to_message: EventToMessage::Event(to_message),
+        stop_propagation: false,
+        prevent_default: false,
+        sync: false,
+        js_closure: Default::default(),
+    })
+}
+
+/// Sends the message produced by `decoder`. Nothing is sent when the event does not decode.
+pub fn on<Msg: 'static>(type_: &str, decoder: Decoder<Msg>) -> Attribute<Msg> {
+    custom(
+        type_,
+        decoder.map(|message| Custom {
+            message,
+            stop_propagation: false,
+            prevent_default: false,
+        }),
+    )
+}
+
+/// Like `on`, but the decoder also decides whether the event stops propagating.
+pub fn stop_propagation_on<Msg: 'static>(
+    type_: &str,
+    decoder: Decoder<(Msg, bool)>,
+) -> Attribute<Msg> {
+    custom(
+        type_,
+        decoder.map(|(message, stop_propagation)| Custom {
+            message,
+            stop_propagation,
+            prevent_default: false,
+        }),
+    )
+}
+
+/// Like `on`, but the decoder also decides whether the default action is prevented.
+pub fn prevent_default_on<Msg: 'static>(
+    type_: &str,
+    decoder: Decoder<(Msg, bool)>,
+) -> Attribute<Msg> {
+    custom(
+        type_,
+        decoder.map(|(message, prevent_default)| Custom {
+            message,
+            stop_propagation: false,
+            prevent_default,
+        }),
+    )
+}
+
+/// The most general event handler, where the decoder decides about both propagation and the
+/// default action.
+pub fn custom<Msg: 'static>(type_: &str, decoder: Decoder<Custom<Msg>>) -> Attribute<Msg> {
+    Attribute::Event(EventListener {
+        type_: type_.to_owned(),
+        to_message: EventToMessage::Decoder(decoder),
+        stop_propagation: false,
         prevent_default: false,
+        sync: false,
         js_closure: Default::default(),
     })
 }
 
-// pub fn on_double_click<Msg: Clone + 'static>(message: Msg) -> Attribute<Msg> {
-//     Attribute::Event(EventListener {
-//         type_: "dblclick".to_owned(),
-//         to_message: EventToMessage::StaticMsg(message),
-//         stop_propagation: false,
-//         prevent_default: false,
-//         js_closure: Default::default(),
-//     })
-// }
-
-// pub fn on_blur<Msg: Clone + 'static>(message: Msg) -> Attribute<Msg> {
-//     Attribute::Event(EventListener {
-//         type_: "blur".to_owned(),
-//         to_message: EventToMessage::StaticMsg(message),
-//         stop_propagation: false,
-//         prevent_default: false,
-//         js_closure: Default::default(),
-//     })
-// }
-
-// // TODO: Ensure that when we start using animationFrame, on_input gets special treatement
-// pub fn on_input<Msg: 'static>(message: fn(String) -> Msg) -> Attribute<Msg> {
-//     Attribute::Event(EventListener {
-//         type_: "input".to_owned(),
-//         to_message: EventToMessage::Input(message),
-//         stop_propagation: true,
-//         prevent_default: false,
-//         js_closure: Default::default(),
-//     })
-// }
+/// What a `custom` event decoder produces
+#[derive(Clone, Debug, PartialEq)]
+pub struct Custom<Msg> {
+    pub message: Msg,
+    pub stop_propagation: bool,
+    pub prevent_default: bool,
+}
+
+/// The `key` of a keyboard event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    Delete,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    /// A key that produces a character, like `a` or ` `
+    Character(String),
+    /// Any other named key, like `Shift` or `F1`
+    Other(String),
+}
+
+impl<'a> From<&'a str> for Key {
+    fn from(key: &'a str) -> Key {
+        match key {
+            "Enter" => Key::Enter,
+            "Escape" | "Esc" => Key::Escape,
+            "Tab" => Key::Tab,
+            "Backspace" => Key::Backspace,
+            "Delete" | "Del" => Key::Delete,
+            "ArrowUp" | "Up" => Key::ArrowUp,
+            "ArrowDown" | "Down" => Key::ArrowDown,
+            "ArrowLeft" | "Left" => Key::ArrowLeft,
+            "ArrowRight" | "Right" => Key::ArrowRight,
+            _ if key.chars().count() == 1 => Key::Character(key.to_owned()),
+            _ => Key::Other(key.to_owned()),
+        }
+    }
+}
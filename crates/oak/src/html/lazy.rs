@@ -0,0 +1,135 @@
+use crate::html::Html;
+#[cfg(feature = "browser")]
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt::{self, Debug};
+use std::rc::Rc;
+
+/// A view that is only rendered when its argument changed, see `lazy`
+#[derive(Clone)]
+pub struct Lazy<Msg>(pub(crate) Rc<dyn LazyNode<Msg>>);
+
+impl<Msg> Debug for Lazy<Msg> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Renders `view(&arg)` lazily. When the previous render had a lazy node with the same view
+/// function and an equal argument, the view function is not called and the old nodes are
+/// kept without diffing them. `Rc` arguments compare by value, so wrap large arguments in a
+/// type whose `PartialEq` is cheap.
+///
+/// A view that renders a fragment or nothing works too, but its nodes are diffed one by one
+/// like the children of a fragment, so only views of a single node skip the diff.
+///
+/// ```
+/// use oak::html::{li, lazy, text, ul, Html};
+///
+/// fn row(name: &String) -> Html<()> {
+///     li([], [text(name)])
+/// }
+///
+/// let names = vec!["Ada".to_owned(), "Grace".to_owned()];
+/// let view: Html<()> = ul([], names.into_iter().map(|name| lazy(row, name)).collect::<Vec<_>>());
+/// assert_eq!(oak::ssr::render_to_string(&view), "<ul><li>Ada</li><li>Grace</li></ul>");
+/// ```
+pub fn lazy<Arg, Msg>(view: fn(&Arg) -> Html<Msg>, arg: Arg) -> Html<Msg>
+where
+    Arg: PartialEq + 'static,
+    Msg: 'static,
+{
+    Html::Lazy(Lazy(Rc::new(LazyView {
+        key: None,
+        view,
+        arg,
+        html: RefCell::new(None),
+    })))
+}
+
+/// Like `lazy`, with a key, so that lists of lazy views can be diffed by key without
+/// rendering them first
+pub fn lazy_keyed<Arg, Msg>(key: String, view: fn(&Arg) -> Html<Msg>, arg: Arg) -> Html<Msg>
+where
+    Arg: PartialEq + 'static,
+    Msg: 'static,
+{
+    Html::Lazy(Lazy(Rc::new(LazyView {
+        key: Some(key),
+        view,
+        arg,
+        html: RefCell::new(None),
+    })))
+}
+
+/// The view of a `Lazy`, with its argument type erased
+pub(crate) trait LazyNode<Msg>: Debug {
+    #[cfg(feature = "browser")]
+    fn as_any(&self) -> &dyn Any;
+
+    fn key(&self) -> Option<&str>;
+
+    #[cfg(feature = "browser")]
+    /// Whether `other` renders the same view, because it has the same view function and an
+    /// equal argument
+    fn same(&self, other: &dyn LazyNode<Msg>) -> bool;
+
+    /// The rendered view, which is only rendered the first time it is needed
+    fn view(&self) -> Rc<Html<Msg>>;
+
+    #[cfg(feature = "browser")]
+    /// Takes over the rendered view of `other`, which is the same
+    fn reuse(&self, other: &dyn LazyNode<Msg>);
+}
+
+struct LazyView<Arg, Msg> {
+    key: Option<String>,
+    view: fn(&Arg) -> Html<Msg>,
+    arg: Arg,
+    html: RefCell<Option<Rc<Html<Msg>>>>,
+}
+
+impl<Arg, Msg> Debug for LazyView<Arg, Msg> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Lazy").field("key", &self.key).finish()
+    }
+}
+
+impl<Arg, Msg> LazyNode<Msg> for LazyView<Arg, Msg>
+where
+    Arg: PartialEq + 'static,
+    Msg: 'static,
+{
+    #[cfg(feature = "browser")]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    #[cfg(feature = "browser")]
+    fn same(&self, other: &dyn LazyNode<Msg>) -> bool {
+        match other.as_any().downcast_ref::<Self>() {
+            Some(other) => self.view as usize == other.view as usize && self.arg == other.arg,
+            None => false,
+        }
+    }
+
+    fn view(&self) -> Rc<Html<Msg>> {
+        if let Some(html) = &*self.html.borrow() {
+            return html.clone();
+        }
+        let html = Rc::new((self.view)(&self.arg));
+        self.html.replace(Some(html.clone()));
+        html
+    }
+
+    #[cfg(feature = "browser")]
+    fn reuse(&self, other: &dyn LazyNode<Msg>) {
+        if let Some(other) = other.as_any().downcast_ref::<Self>() {
+            self.html.replace(other.html.borrow().clone());
+        }
+    }
+}
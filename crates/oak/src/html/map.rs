@@ -0,0 +1,241 @@
+#[cfg(feature = "browser")]
+use crate::html::events::Custom;
+use crate::html::{EventToMessage, Html};
+#[cfg(feature = "browser")]
+use crate::render::Renderer;
+use std::any::Any;
+use std::fmt::{self, Debug};
+use std::rc::Rc;
+#[cfg(feature = "browser")]
+use wasm_bindgen::JsValue;
+
+/// A view whose messages are turned into `Msg` as they are dispatched. Created by `Html::map`,
+/// which does not have to copy the view for that.
+#[derive(Clone)]
+pub struct Mapped<Msg>(pub(crate) Rc<dyn MapNode<Msg>>);
+
+impl<Msg> Mapped<Msg> {
+    pub(crate) fn new<Child>(html: Html<Child>, mapper: Rc<dyn Fn(Child) -> Msg>) -> Self
+    where
+        Child: PartialEq + Debug + Clone + 'static,
+        Msg: PartialEq + Debug + Clone + 'static,
+    {
+        Mapped(Rc::new(MappedNode { html, mapper }))
+    }
+}
+
+impl<Msg> Debug for Mapped<Msg> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The view of a `Mapped`, with its message type erased
+pub(crate) trait MapNode<Msg>: Debug {
+    #[cfg(feature = "browser")]
+    fn as_any(&self) -> &dyn Any;
+
+    fn key(&self) -> Option<&str>;
+
+    fn write_html(&self, output: &mut String);
+
+    #[cfg(feature = "browser")]
+    fn create_node(&self, renderer: &Renderer<Msg>) -> Result<web_sys::Node, JsValue>;
+
+    /// Patches `node`, which was rendered from `old`. Views of another message type are
+    /// replaced instead.
+    #[cfg(feature = "browser")]
+    fn patch(
+        &self,
+        renderer: &Renderer<Msg>,
+        parent: &web_sys::Node,
+        node: &web_sys::Node,
+        old: &dyn MapNode<Msg>,
+    ) -> Result<web_sys::Node, JsValue>;
+
+    #[cfg(feature = "browser")]
+    fn hydrate(
+        &self,
+        renderer: &Renderer<Msg>,
+        parent: &web_sys::Node,
+        node: Option<web_sys::Node>,
+    ) -> Result<(), JsValue>;
+
+    /// See `Html::expand`
+    #[cfg(feature = "browser")]
+    fn expand(&self) -> Option<Vec<Html<Msg>>>;
+
+    /// See `Html::reuse_lazy`
+    #[cfg(feature = "browser")]
+    fn reuse_lazy(&self, old: &dyn MapNode<Msg>);
+}
+
+struct MappedNode<Child, Msg> {
+    html: Html<Child>,
+    // Messages only exist in the browser
+    #[cfg_attr(not(feature = "browser"), allow(dead_code))]
+    mapper: Rc<dyn Fn(Child) -> Msg>,
+}
+
+impl<Child: Debug, Msg> Debug for MappedNode<Child, Msg> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Mapped").field(&self.html).finish()
+    }
+}
+
+impl<Child, Msg> MapNode<Msg> for MappedNode<Child, Msg>
+where
+    Child: PartialEq + Debug + Clone + 'static,
+    Msg: PartialEq + Debug + Clone + 'static,
+{
+    #[cfg(feature = "browser")]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn key(&self) -> Option<&str> {
+        self.html.key()
+    }
+
+    fn write_html(&self, output: &mut String) {
+        crate::ssr::write_html(output, &self.html)
+    }
+
+    #[cfg(feature = "browser")]
+    fn create_node(&self, renderer: &Renderer<Msg>) -> Result<web_sys::Node, JsValue> {
+        renderer.map(self.mapper.clone()).create_node(&self.html)
+    }
+
+    #[cfg(feature = "browser")]
+    fn patch(
+        &self,
+        renderer: &Renderer<Msg>,
+        parent: &web_sys::Node,
+        node: &web_sys::Node,
+        old: &dyn MapNode<Msg>,
+    ) -> Result<web_sys::Node, JsValue> {
+        let mut renderer = renderer.map(self.mapper.clone());
+        let node = match old.as_any().downcast_ref::<Self>() {
+            Some(old) => renderer.patch(parent, node, &old.html, &self.html)?,
+            None => {
+                let new_node = renderer.create_node(&self.html)?;
+                parent.replace_child(&new_node, node)?;
+                new_node
+            }
+        };
+        renderer.finish()?;
+        Ok(node)
+    }
+
+    #[cfg(feature = "browser")]
+    fn hydrate(
+        &self,
+        renderer: &Renderer<Msg>,
+        parent: &web_sys::Node,
+        node: Option<web_sys::Node>,
+    ) -> Result<(), JsValue> {
+        renderer
+            .map(self.mapper.clone())
+            .hydrate_node(parent, node, &self.html)
+    }
+
+    #[cfg(feature = "browser")]
+    fn expand(&self) -> Option<Vec<Html<Msg>>> {
+        let nodes = self.html.expand()?;
+        Some(
+            nodes
+                .into_iter()
+                .map(|node| Html::Map(Mapped::new(node, self.mapper.clone())))
+                .collect(),
+        )
+    }
+
+    #[cfg(feature = "browser")]
+    fn reuse_lazy(&self, old: &dyn MapNode<Msg>) {
+        if let Some(old) = old.as_any().downcast_ref::<Self>() {
+            self.html.reuse_lazy(&old.html);
+        }
+    }
+}
+
+/// An event handler whose messages are turned into `Msg` as it sends them. Created by
+/// `Attribute::map`, which keeps the handler it maps.
+#[derive(Clone)]
+pub struct MappedHandler<Msg>(pub(crate) Rc<dyn MapHandler<Msg>>);
+
+impl<Msg> MappedHandler<Msg> {
+    pub(crate) fn new<Child>(
+        handler: EventToMessage<Child>,
+        mapper: Rc<dyn Fn(Child) -> Msg>,
+    ) -> Self
+    where
+        Child: PartialEq + Debug + Clone + 'static,
+        Msg: 'static,
+    {
+        MappedHandler(Rc::new(MappedEventHandler { handler, mapper }))
+    }
+}
+
+impl<Msg> Debug for MappedHandler<Msg> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<Msg> PartialEq for MappedHandler<Msg> {
+    /// Mapped handlers are equal when they map equal handlers. Mappers are closures, so
+    /// they are not compared.
+    fn eq(&self, other: &MappedHandler<Msg>) -> bool {
+        self.0.same(&*other.0)
+    }
+}
+
+/// The handler of a `MappedHandler`, with its message type erased
+pub(crate) trait MapHandler<Msg>: Debug {
+    fn as_any(&self) -> &dyn Any;
+
+    fn same(&self, other: &dyn MapHandler<Msg>) -> bool;
+
+    #[cfg(feature = "browser")]
+    fn handle(&self, event: &web_sys::Event) -> Option<Custom<Msg>>;
+}
+
+struct MappedEventHandler<Child, Msg> {
+    handler: EventToMessage<Child>,
+    // Messages only exist in the browser
+    #[cfg_attr(not(feature = "browser"), allow(dead_code))]
+    mapper: Rc<dyn Fn(Child) -> Msg>,
+}
+
+impl<Child: Debug, Msg> Debug for MappedEventHandler<Child, Msg> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Mapped").field(&self.handler).finish()
+    }
+}
+
+impl<Child, Msg> MapHandler<Msg> for MappedEventHandler<Child, Msg>
+where
+    Child: PartialEq + Debug + Clone + 'static,
+    Msg: 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn same(&self, other: &dyn MapHandler<Msg>) -> bool {
+        match other.as_any().downcast_ref::<Self>() {
+            Some(other) => self.handler == other.handler,
+            None => false,
+        }
+    }
+
+    #[cfg(feature = "browser")]
+    fn handle(&self, event: &web_sys::Event) -> Option<Custom<Msg>> {
+        let custom = self.handler.handle(event)?;
+        Some(Custom {
+            message: (self.mapper)(custom.message),
+            stop_propagation: custom.stop_propagation,
+            prevent_default: custom.prevent_default,
+        })
+    }
+}
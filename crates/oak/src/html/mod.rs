@@ -1,17 +1,200 @@
 pub mod attributes;
+pub mod decoder;
 pub mod events;
 
 mod elements;
+mod lazy;
+mod map;
 pub use self::elements::*;
+pub use self::lazy::{lazy, lazy_keyed, Lazy};
+pub use self::map::{Mapped, MappedHandler};
 
+/// Tests of the `html!` macro, which is only built for doctests. First, what a view with
+/// attributes, a loop, a condition and a fragment expands to:
+///
+/// ```
+/// use oak::html::attributes::{class, id, key};
+/// use oak::html::events::on_click;
+/// use oak::html::{button, fragment, li, p, text, ul, Attribute, Html};
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// enum Msg {
+///     Remove(usize),
+/// }
+///
+/// let items = vec!["a", "b"];
+/// let empty = false;
+/// let view: Html<Msg> = oak::html! {
+///     <>
+///         <ul class="items" data-count={items.len()}>
+///             {for items.iter().enumerate().map(|(index, item)| oak::html! {
+///                 <li key={item}><button on_click={Msg::Remove(index)}>{*item}</button></li>
+///             })}
+///         </ul>
+///         if empty {
+///             <p>"Nothing"</p>
+///         } else {
+///             <p id="count">{items.len().to_string()}</p>
+///         }
+///     </>
+/// };
+///
+/// let data_count = Attribute::Text("data-count".to_owned(), "2".to_owned());
+/// let expected: Html<Msg> = fragment([
+///     ul(
+///         [class("items"), data_count],
+///         [
+///             li([key("a")], [button([on_click(Msg::Remove(0))], ["a"])]),
+///             li([key("b")], [button([on_click(Msg::Remove(1))], ["b"])]),
+///         ],
+///     ),
+///     p([id("count")], [text("2")]),
+/// ]);
+/// assert_eq!(format!("{:?}", view), format!("{:?}", expected));
+/// ```
+///
+/// Then the views it rejects, which must keep failing to compile. An unknown element:
+///
+/// ```compile_fail
+/// let view: oak::html::Html<()> = oak::html! { <blink>"Hi"</blink> };
+/// ```
+///
+/// An attribute of another element:
+///
+/// ```compile_fail
+/// let view: oak::html::Html<()> = oak::html! { <div href="/">"Home"</div> };
+/// ```
+///
+/// A row directly inside a table:
+///
+/// ```compile_fail
+/// let view: oak::html::Html<()> = oak::html! { <table><tr><td>"1"</td></tr></table> };
+/// ```
+///
+/// A list item outside of a list:
+///
+/// ```compile_fail
+/// let view: oak::html::Html<()> = oak::html! { <div><li>"Item"</li></div> };
+/// ```
+///
+/// A button inside a link:
+///
+/// ```compile_fail
+/// let view: oak::html::Html<()> = oak::html! { <a href="/"><button>"Go"</button></a> };
+/// ```
+///
+/// A block inside a paragraph:
+///
+/// ```compile_fail
+/// let view: oak::html::Html<()> = oak::html! { <p><div>"Block"</div></p> };
+/// ```
+///
+/// A closing tag on a void element:
+///
+/// ```compile_fail
+/// let view: oak::html::Html<()> = oak::html! { <div><br></br></div> };
+/// ```
+#[cfg(doctest)]
+pub struct HtmlMacroTests;
+
+use std::any::Any;
 use std::cell::RefCell;
+use std::fmt::Debug;
 use std::rc::Rc;
-use wasm_bindgen::closure::Closure;
 
 #[derive(Clone, Debug)]
 pub enum Html<Msg> {
     Element(Element<Msg>),
     Text(String),
+    /// A view with another message type, see `Html::map`
+    Map(Mapped<Msg>),
+    /// Several siblings without an element around them
+    Fragment(Vec<Html<Msg>>),
+    /// Renders nothing at all
+    None,
+    /// A view that is only rendered when its argument changed, see `lazy`
+    Lazy(Lazy<Msg>),
+}
+
+impl<Msg> Html<Msg> {
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            Html::Element(element) => element.key(),
+            Html::Map(mapped) => mapped.0.key(),
+            Html::Lazy(lazy) => lazy.0.key(),
+            Html::Text(_) | Html::Fragment(_) | Html::None => None,
+        }
+    }
+}
+
+#[cfg(feature = "browser")]
+impl<Msg: Clone> Html<Msg> {
+    /// The nodes this view stands for, when that is not exactly one node. Children are lined
+    /// up with the nodes of the DOM, so fragments and empty views are flattened, and so are
+    /// lazy and mapped views that render one of them.
+    pub(crate) fn expand(&self) -> Option<Vec<Html<Msg>>> {
+        match self {
+            Html::Element(_) | Html::Text(_) => None,
+            Html::Fragment(children) => Some(
+                children
+                    .iter()
+                    .flat_map(|child| child.expand().unwrap_or_else(|| vec![child.clone()]))
+                    .collect(),
+            ),
+            Html::None => Some(vec![]),
+            Html::Lazy(lazy) => lazy.0.view().expand(),
+            Html::Map(mapped) => mapped.0.expand(),
+        }
+    }
+
+    /// Lets the lazy views in this view take over the rendered views of the same lazy views
+    /// in `old`, so that expanding them does not render them again
+    pub(crate) fn reuse_lazy(&self, old: &Html<Msg>) {
+        match (old, self) {
+            (Html::Lazy(old), Html::Lazy(new)) if new.0.same(&*old.0) => new.0.reuse(&*old.0),
+            (Html::Map(old), Html::Map(new)) => new.0.reuse_lazy(&*old.0),
+            (Html::Fragment(old), Html::Fragment(new)) => {
+                for (old, new) in old.iter().zip(new) {
+                    new.reuse_lazy(old);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<Msg> Html<Msg>
+where
+    Msg: PartialEq + Debug + Clone + 'static,
+{
+    /// Turns the messages of this view into `Parent` messages, so the view of a child
+    /// component can be embedded in the view of its parent. The view is not copied, its
+    /// messages are mapped when they are dispatched.
+    pub fn map<Parent, F>(self, f: F) -> Html<Parent>
+    where
+        Parent: PartialEq + Debug + Clone + 'static,
+        F: Fn(Msg) -> Parent + 'static,
+    {
+        self.map_rc(Rc::new(f))
+    }
+
+    fn map_rc<Parent>(self, f: Rc<dyn Fn(Msg) -> Parent>) -> Html<Parent>
+    where
+        Parent: PartialEq + Debug + Clone + 'static,
+    {
+        match self {
+            // Fragments are mapped child by child, so the renderer only has to flatten mapped
+            // views that are lazy
+            Html::Fragment(children) => Html::Fragment(
+                children
+                    .into_iter()
+                    .map(|child| child.map_rc(f.clone()))
+                    .collect(),
+            ),
+            Html::None => Html::None,
+            html => Html::Map(Mapped::new(html, f)),
+        }
+    }
 }
 
 impl<T: ToString, Msg> From<T> for Html<Msg> {
@@ -23,6 +206,8 @@ impl<T: ToString, Msg> From<T> for Html<Msg> {
 #[derive(Clone, Debug)]
 pub struct Element<Msg> {
     pub name: String,
+    /// The namespace of elements that are not HTML, like SVG
+    pub namespace: Option<String>,
     pub attrs: Vec<Attribute<Msg>>,
     pub children: Children<Msg>,
 }
@@ -53,22 +238,104 @@ impl<Msg> Element<Msg> {
 #[derive(Clone, Debug, PartialEq)]
 pub enum Attribute<Msg> {
     Text(String, String),
-    Bool(String),
+    /// An attribute in a namespace, like `xlink:href`, as namespace, qualified name and value
+    Namespaced(String, String, String),
+    /// An attribute that is present when `true` and absent when `false`, like `hidden`
+    Bool(String, bool),
+    /// A property set on the DOM node instead of an attribute. Properties like `value` and
+    /// `checked` reflect the current state of an input, not just its initial one.
+    Property(String, Value),
+    /// Inline style properties, as name and value pairs
+    Style(Vec<(String, String)>),
     Key(String),
     Event(EventListener<Msg>),
 }
 
+impl<Msg> Attribute<Msg>
+where
+    Msg: PartialEq + Debug + Clone + 'static,
+{
+    /// Turns the messages of an event handler into `Parent` messages. Other attributes stay
+    /// the same.
+    pub fn map<Parent: 'static, F>(self, f: F) -> Attribute<Parent>
+    where
+        F: Fn(Msg) -> Parent + 'static,
+    {
+        match self {
+            Attribute::Text(name, value) => Attribute::Text(name, value),
+            Attribute::Namespaced(namespace, name, value) => {
+                Attribute::Namespaced(namespace, name, value)
+            }
+            Attribute::Bool(name, value) => Attribute::Bool(name, value),
+            Attribute::Property(name, value) => Attribute::Property(name, value),
+            Attribute::Style(properties) => Attribute::Style(properties),
+            Attribute::Key(key) => Attribute::Key(key),
+            Attribute::Event(listener) => Attribute::Event(EventListener {
+                js_closure: Default::default(),
+                type_: listener.type_,
+                stop_propagation: listener.stop_propagation,
+                prevent_default: listener.prevent_default,
+                sync: listener.sync,
+                to_message: listener.to_message.map(f),
+            }),
+        }
+    }
+}
+
+/// A plain JavaScript value, as read from an event or set as a DOM property
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value {
+        Value::Number(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Value {
+        Value::Number(f64::from(value))
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(value: &'a str) -> Value {
+        Value::String(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::String(value)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct EventListener<Msg> {
     pub js_closure: JsClosure,
     pub type_: String,
     pub stop_propagation: bool,
     pub prevent_default: bool,
+    /// Render right after the message is handled instead of on the next animation frame
+    pub sync: bool,
     pub to_message: EventToMessage<Msg>,
 }
 
+/// The closure the renderer attached to the DOM for an event listener. It is type erased so
+/// that the virtual DOM does not depend on the browser runtime.
 #[derive(Clone, Default)]
-pub struct JsClosure(pub Rc<RefCell<Option<Closure<Fn(web_sys::Event)>>>>);
+pub struct JsClosure(pub Rc<RefCell<Option<Box<dyn Any>>>>);
 
 impl std::fmt::Debug for JsClosure {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -88,11 +355,119 @@ impl std::cmp::PartialEq for JsClosure {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub enum EventToMessage<Msg> {
     StaticMsg(Msg),
+    /// Gets the `value` of the event target
+    Input(fn(String) -> Msg),
+    /// Gets the `checked` state of the event target
+    Check(fn(bool) -> Msg),
+    /// Gets the `key` of a keyboard event
+    Key(fn(events::Key) -> Msg),
+    /// Gets the raw event, and may decide not to send a message at all
+    #[cfg(feature = "browser")]
+    Event(fn(&web_sys::Event) -> Option<Msg>),
+    /// Decodes the event, and decides whether to stop propagation or prevent the default
+    /// action based on it. No message is sent when decoding fails.
+    Decoder(decoder::Decoder<events::Custom<Msg>>),
+    /// A handler with another message type, see `Attribute::map`
+    Mapped(MappedHandler<Msg>),
+}
+
+impl<Msg: PartialEq> PartialEq for EventToMessage<Msg> {
+    /// Handlers are equal when they send the same message or call the same function.
+    /// Function pointers are compared by address, and one function may have several, so at
+    /// worst an unchanged handler is bound again.
+    fn eq(&self, other: &EventToMessage<Msg>) -> bool {
+        match (self, other) {
+            (EventToMessage::StaticMsg(a), EventToMessage::StaticMsg(b)) => a == b,
+            (EventToMessage::Input(a), EventToMessage::Input(b)) => *a as usize == *b as usize,
+            (EventToMessage::Check(a), EventToMessage::Check(b)) => *a as usize == *b as usize,
+            (EventToMessage::Key(a), EventToMessage::Key(b)) => *a as usize == *b as usize,
+            #[cfg(feature = "browser")]
+            (EventToMessage::Event(a), EventToMessage::Event(b)) => *a as usize == *b as usize,
+            (EventToMessage::Decoder(a), EventToMessage::Decoder(b)) => a == b,
+            (EventToMessage::Mapped(a), EventToMessage::Mapped(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<Msg> EventToMessage<Msg>
+where
+    Msg: PartialEq + Debug + Clone + 'static,
+{
+    /// Turns the produced messages into `Parent` messages. The handler is kept as it is, and
+    /// its messages are mapped when it sends them.
+    pub fn map<Parent: 'static, F>(self, f: F) -> EventToMessage<Parent>
+    where
+        F: Fn(Msg) -> Parent + 'static,
+    {
+        match self {
+            EventToMessage::StaticMsg(message) => EventToMessage::StaticMsg(f(message)),
+            handler => EventToMessage::Mapped(MappedHandler::new(handler, Rc::new(f))),
+        }
+    }
+
+    /// The message for `event`, if there is one, with whether the event should stop
+    /// propagating and have its default action prevented
+    #[cfg(feature = "browser")]
+    pub(crate) fn handle(&self, event: &web_sys::Event) -> Option<events::Custom<Msg>> {
+        let message = match self {
+            EventToMessage::StaticMsg(message) => message.clone(),
+            EventToMessage::Input(to_message) => {
+                to_message(decoder::target_value().decode(event).ok()?)
+            }
+            EventToMessage::Check(to_message) => {
+                to_message(decoder::target_checked().decode(event).ok()?)
+            }
+            EventToMessage::Key(to_message) => to_message(decoder::key().decode(event).ok()?),
+            EventToMessage::Event(to_message) => to_message(event)?,
+            EventToMessage::Decoder(decoder) => {
+                return match decoder.decode(event) {
+                    Ok(custom) => Some(custom),
+                    Err(error) => {
+                        log::debug!("Ignoring {} event: {}", event.type_(), error);
+                        None
+                    }
+                };
+            }
+            EventToMessage::Mapped(handler) => return handler.0.handle(event),
+        };
+        Some(events::Custom {
+            message,
+            stop_propagation: false,
+            prevent_default: false,
+        })
+    }
 }
 
 pub fn text<Msg, S: Into<String>>(inner: S) -> Html<Msg> {
     Html::Text(inner.into())
 }
+
+/// Siblings without an element around them, so a view can return several nodes
+pub fn fragment<Msg: Clone, H: Into<Html<Msg>> + Clone, C: AsRef<[H]>>(children: C) -> Html<Msg> {
+    Html::Fragment(
+        children
+            .as_ref()
+            .to_vec()
+            .into_iter()
+            .map(|c| c.into())
+            .collect(),
+    )
+}
+
+/// A view that renders nothing
+pub fn none<Msg>() -> Html<Msg> {
+    Html::None
+}
+
+/// Renders the result of `view` when `condition` holds, and nothing otherwise
+pub fn when<Msg, F: FnOnce() -> Html<Msg>>(condition: bool, view: F) -> Html<Msg> {
+    if condition {
+        view()
+    } else {
+        Html::None
+    }
+}
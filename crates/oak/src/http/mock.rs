@@ -0,0 +1,334 @@
+use crate::http::{
+    Abort, Backend, Body, Error, Progress, Request, Response, ResponseBody, ResponseType,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::prelude::JsValue;
+
+/// A backend for tests, which answers requests with canned responses instead of sending
+/// them, and records every request it got.
+///
+/// Responses are only delivered when the test moves the mock's virtual clock with `advance`
+/// or `flush`, never while a command runs. Requests that match no stub get a 404. Tracked
+/// requests report all of their body as sent and all of the response as received right
+/// before they are answered.
+///
+/// ```
+/// use oak::http::{self, Error, MockBackend};
+/// use oak::{Cmd, Dispatcher};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use std::time::Duration;
+///
+/// let mock = MockBackend::new();
+/// mock.on("GET", "/greeting")
+///     .delay(Duration::from_millis(100))
+///     .respond(200, "Hello");
+/// http::set_backend(mock.clone());
+///
+/// let messages = Rc::new(RefCell::new(vec![]));
+/// let received = messages.clone();
+/// let dispatcher = Dispatcher::new(move |message| received.borrow_mut().push(message));
+/// http::get("/greeting")
+///     .send(http::expect_text(), |result: Result<String, Error>| result)
+///     .run(&dispatcher)
+///     .unwrap();
+/// assert_eq!(mock.requests()[0].url, "/greeting");
+///
+/// mock.advance(Duration::from_millis(50));
+/// assert!(messages.borrow().is_empty());
+/// mock.advance(Duration::from_millis(50));
+/// assert_eq!(*messages.borrow(), vec![Ok("Hello".to_owned())]);
+/// ```
+#[derive(Clone, Default)]
+pub struct MockBackend(Rc<RefCell<State>>);
+
+#[derive(Default)]
+struct State {
+    stubs: Vec<StubEntry>,
+    requests: Vec<Request>,
+    pending: Vec<Pending>,
+    now: Duration,
+    sent: usize,
+}
+
+struct StubEntry {
+    method: String,
+    url: String,
+    delay: Duration,
+    outcome: Result<Response, Error>,
+}
+
+/// A request that was sent, with the outcome it gets at `due`
+struct Pending {
+    due: Duration,
+    order: usize,
+    outcome: Result<Response, Error>,
+    size: u64,
+    progress: Box<dyn Fn(Progress)>,
+    done: Box<dyn FnOnce(Result<Response, Error>)>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        MockBackend::default()
+    }
+
+    /// Stubs requests with `method` to `url`. A later stub of the same request replaces the
+    /// earlier one.
+    pub fn on(&self, method: &str, url: &str) -> Stub {
+        Stub {
+            backend: self.clone(),
+            method: method.to_owned(),
+            url: url.to_owned(),
+            delay: Duration::from_secs(0),
+        }
+    }
+
+    /// Every request that was sent so far, in order
+    pub fn requests(&self) -> Vec<Request> {
+        self.0.borrow().requests.clone()
+    }
+
+    /// How many requests have not been answered yet
+    pub fn pending(&self) -> usize {
+        self.0.borrow().pending.len()
+    }
+
+    /// The virtual time, which starts at zero
+    pub fn now(&self) -> Duration {
+        self.0.borrow().now
+    }
+
+    /// Moves the virtual clock forward by `duration`, and answers the requests that are due
+    /// by then in order, including those sent by the messages of earlier answers
+    pub fn advance(&self, duration: Duration) {
+        let until = self.now() + duration;
+        while self.answer_next(Some(until)) {}
+        self.0.borrow_mut().now = until;
+    }
+
+    /// Answers every pending request in order, moving the virtual clock as far as needed
+    pub fn flush(&self) {
+        while self.answer_next(None) {}
+    }
+
+    /// Answers the request that is due next, unless it is due after `until`
+    fn answer_next(&self, until: Option<Duration>) -> bool {
+        let pending = {
+            let mut state = self.0.borrow_mut();
+            let next = state
+                .pending
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, pending)| (pending.due, pending.order))
+                .map(|(index, pending)| (index, pending.due));
+            match next {
+                Some((index, due)) if !matches!(until, Some(until) if due > until) => {
+                    state.now = state.now.max(due);
+                    state.pending.remove(index)
+                }
+                _ => return false,
+            }
+        };
+        // The state is not borrowed here, as the message may send more requests
+        if let Ok(response) = &pending.outcome {
+            let size = pending.size;
+            (pending.progress)(Progress::Sending { sent: size, size });
+            let size = match &response.body {
+                ResponseBody::Text(text) => text.len(),
+                ResponseBody::Bytes(bytes) => bytes.len(),
+            } as u64;
+            (pending.progress)(Progress::Receiving {
+                received: size,
+                size: Some(size),
+            });
+        }
+        (pending.done)(pending.outcome);
+        true
+    }
+}
+
+impl Backend for MockBackend {
+    fn send(
+        &self,
+        request: &Request,
+        _: ResponseType,
+        progress: Box<dyn Fn(Progress)>,
+        done: Box<dyn FnOnce(Result<Response, Error>)>,
+    ) -> Result<Abort, JsValue> {
+        let mut state = self.0.borrow_mut();
+        let stub = state.stubs.iter().rev().find(|stub| {
+            stub.method.eq_ignore_ascii_case(&request.method) && stub.url == request.url
+        });
+        let (delay, outcome) = match stub {
+            Some(stub) => (stub.delay, stub.outcome.clone()),
+            None => (
+                Duration::from_secs(0),
+                Ok(Response {
+                    status: 404,
+                    body: ResponseBody::Text(String::new()),
+                }),
+            ),
+        };
+        let (delay, outcome) = match request.timeout {
+            Some(timeout) if timeout < delay => (timeout, Err(Error::Timeout)),
+            _ => (delay, outcome),
+        };
+        let order = state.sent;
+        let due = state.now + delay;
+        state.sent += 1;
+        state.requests.push(request.clone());
+        let size = match &request.body {
+            Body::Empty => 0,
+            Body::Text { content, .. } => content.len(),
+            Body::Bytes { content, .. } => content.len(),
+        } as u64;
+        state.pending.push(Pending {
+            due,
+            order,
+            outcome,
+            size,
+            progress,
+            done,
+        });
+        let backend = self.clone();
+        Ok(Abort::new(move || {
+            backend
+                .0
+                .borrow_mut()
+                .pending
+                .retain(|pending| pending.order != order)
+        }))
+    }
+}
+
+/// The answer of a `MockBackend` to some request, see `MockBackend::on`
+pub struct Stub {
+    backend: MockBackend,
+    method: String,
+    url: String,
+    delay: Duration,
+}
+
+impl Stub {
+    /// Answers after `delay` of virtual time, instead of right away
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn respond(self, status: u16, body: &str) {
+        self.answer(Ok(Response {
+            status,
+            body: ResponseBody::Text(body.to_owned()),
+        }))
+    }
+
+    pub fn respond_bytes(self, status: u16, body: Vec<u8>) {
+        self.answer(Ok(Response {
+            status,
+            body: ResponseBody::Bytes(body),
+        }))
+    }
+
+    /// Fails the request with `error`, like `Error::NetworkError`
+    pub fn fail(self, error: Error) {
+        self.answer(Err(error))
+    }
+
+    fn answer(self, outcome: Result<Response, Error>) {
+        self.backend.0.borrow_mut().stubs.push(StubEntry {
+            method: self.method,
+            url: self.url,
+            delay: self.delay,
+            outcome,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http;
+
+    type Outcomes = Rc<RefCell<Vec<Result<Response, Error>>>>;
+
+    fn send(mock: &MockBackend, request: Request) -> Outcomes {
+        let outcomes = Outcomes::default();
+        let received = outcomes.clone();
+        mock.send(
+            &request,
+            ResponseType::Text,
+            Box::new(|_| {}),
+            Box::new(move |outcome| received.borrow_mut().push(outcome)),
+        )
+        .unwrap();
+        outcomes
+    }
+
+    fn ok(status: u16, body: &str) -> Result<Response, Error> {
+        Ok(Response {
+            status,
+            body: ResponseBody::Text(body.to_owned()),
+        })
+    }
+
+    #[test]
+    fn answers_when_the_virtual_clock_reaches_the_delay() {
+        let mock = MockBackend::new();
+        mock.on("GET", "/slow")
+            .delay(Duration::from_millis(100))
+            .respond(200, "slow");
+        mock.on("GET", "/fast")
+            .delay(Duration::from_millis(10))
+            .respond(200, "fast");
+        let slow = send(&mock, http::get("/slow"));
+        let fast = send(&mock, http::get("/fast"));
+        assert_eq!(mock.pending(), 2);
+
+        mock.advance(Duration::from_millis(10));
+        assert_eq!(*fast.borrow(), vec![ok(200, "fast")]);
+        assert!(slow.borrow().is_empty());
+        assert_eq!(mock.now(), Duration::from_millis(10));
+
+        mock.flush();
+        assert_eq!(*slow.borrow(), vec![ok(200, "slow")]);
+        assert_eq!(mock.now(), Duration::from_millis(100));
+        assert_eq!(mock.pending(), 0);
+    }
+
+    #[test]
+    fn times_out_before_a_longer_delay() {
+        let mock = MockBackend::new();
+        mock.on("GET", "/slow")
+            .delay(Duration::from_secs(5))
+            .respond(200, "slow");
+        let outcomes = send(&mock, http::get("/slow").timeout(Duration::from_secs(1)));
+
+        mock.advance(Duration::from_secs(1));
+        assert_eq!(*outcomes.borrow(), vec![Err(Error::Timeout)]);
+        mock.flush();
+        assert_eq!(outcomes.borrow().len(), 1);
+    }
+
+    #[test]
+    fn answers_unstubbed_requests_with_not_found() {
+        let mock = MockBackend::new();
+        mock.on("GET", "/greeting").respond(200, "Hello");
+        let outcomes = send(&mock, http::post("/greeting", Body::Empty));
+        mock.flush();
+        assert_eq!(*outcomes.borrow(), vec![ok(404, "")]);
+    }
+
+    #[test]
+    fn records_requests_in_order() {
+        let mock = MockBackend::new();
+        let first = http::get("/first").header("Accept", "text/plain");
+        let second = http::post("/second", Body::text("text/plain", "hi"));
+        send(&mock, first.clone());
+        send(&mock, second.clone());
+        assert_eq!(mock.requests(), vec![first, second]);
+    }
+}
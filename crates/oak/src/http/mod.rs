@@ -0,0 +1,344 @@
+//! Commands for HTTP requests. A request is built with `get`, `post` or `request`, and
+//! `send` turns it into a command that hands the decoded response to a message. Requests
+//! marked with `track` report their progress to the `progress` subscription, and can be
+//! aborted with `cancel`.
+//!
+//! ```no_run
+//! use oak::http::{self, Error};
+//! use oak::Cmd;
+//!
+//! enum Msg {
+//!     Loaded(Result<String, Error>),
+//! }
+//!
+//! fn load_readme() -> impl Cmd<Msg> {
+//!     http::get("/README.md")
+//!         .header("Accept", "text/markdown")
+//!         .send(http::expect_text(), Msg::Loaded)
+//! }
+//! ```
+
+mod mock;
+mod track;
+mod xhr;
+
+pub use self::mock::{MockBackend, Stub};
+pub use self::track::{cancel, progress, track, Cancel, Progress, Tracking};
+pub use self::xhr::XhrBackend;
+use crate::platform::{Cmd, Dispatcher};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+pub use std::time::Duration;
+use wasm_bindgen::prelude::JsValue;
+
+/// An HTTP request, which is sent by the command `send` returns
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Body,
+    pub timeout: Option<Duration>,
+    pub with_credentials: bool,
+    /// The tracker of the request, see `track`
+    pub tracker: Option<String>,
+}
+
+/// A request with the given method, like `"PUT"` or `"DELETE"`
+pub fn request(method: &str, url: &str) -> Request {
+    Request {
+        method: method.to_owned(),
+        url: url.to_owned(),
+        headers: vec![],
+        body: Body::Empty,
+        timeout: None,
+        with_credentials: false,
+        tracker: None,
+    }
+}
+
+pub fn get(url: &str) -> Request {
+    request("GET", url)
+}
+
+pub fn post(url: &str, body: Body) -> Request {
+    request("POST", url).body(body)
+}
+
+impl Request {
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Sets the body. Its content type is sent as the `Content-Type` header, unless the
+    /// request has a header of that name already.
+    pub fn body(mut self, body: Body) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Gives up on the request with `Error::Timeout` after `timeout`. There is no timeout by
+    /// default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Whether cookies and authorization headers are sent to other origins too
+    pub fn with_credentials(mut self, with_credentials: bool) -> Self {
+        self.with_credentials = with_credentials;
+        self
+    }
+
+    /// A command that sends the request, decodes the response with `expect` and hands the
+    /// result to `to_msg`
+    pub fn send<T, Msg>(
+        self,
+        expect: Expect<T>,
+        to_msg: fn(Result<T, Error>) -> Msg,
+    ) -> Send<T, Msg> {
+        Send {
+            request: self,
+            expect,
+            to_msg,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Body {
+    Empty,
+    Text {
+        content_type: String,
+        content: String,
+    },
+    Bytes {
+        content_type: String,
+        content: Vec<u8>,
+    },
+}
+
+impl Body {
+    pub fn text(content_type: &str, content: &str) -> Self {
+        Body::Text {
+            content_type: content_type.to_owned(),
+            content: content.to_owned(),
+        }
+    }
+
+    pub fn bytes(content_type: &str, content: Vec<u8>) -> Self {
+        Body::Bytes {
+            content_type: content_type.to_owned(),
+            content,
+        }
+    }
+
+    /// `value` as JSON. This fails for values that have no JSON representation, like maps
+    /// with keys that are not strings.
+    pub fn json<T: serde::Serialize>(value: &T) -> Result<Self, serde_json::Error> {
+        Ok(Body::Text {
+            content_type: "application/json".to_owned(),
+            content: serde_json::to_string(value)?,
+        })
+    }
+
+    pub(crate) fn content_type(&self) -> Option<&str> {
+        match self {
+            Body::Empty => None,
+            Body::Text { content_type, .. } | Body::Bytes { content_type, .. } => {
+                Some(content_type)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The URL could not be parsed
+    BadUrl(String),
+    Timeout,
+    /// The server could not be reached, or the browser refused the request, like for CORS
+    NetworkError,
+    /// The response had a status outside of 200–299
+    BadStatus(u16),
+    /// The response body could not be decoded
+    BadBody(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::BadUrl(url) => write!(f, "bad URL: {}", url),
+            Error::Timeout => write!(f, "the request timed out"),
+            Error::NetworkError => write!(f, "network error"),
+            Error::BadStatus(status) => write!(f, "bad status: {}", status),
+            Error::BadBody(error) => write!(f, "bad body: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// How the body of a response is read and decoded into a `T`
+pub struct Expect<T> {
+    response_type: ResponseType,
+    decode: fn(ResponseBody) -> Result<T, String>,
+}
+
+pub fn expect_text() -> Expect<String> {
+    Expect {
+        response_type: ResponseType::Text,
+        decode: |body| match body {
+            ResponseBody::Text(text) => Ok(text),
+            ResponseBody::Bytes(bytes) => {
+                String::from_utf8(bytes).map_err(|error| error.to_string())
+            }
+        },
+    }
+}
+
+pub fn expect_bytes() -> Expect<Vec<u8>> {
+    Expect {
+        response_type: ResponseType::Bytes,
+        decode: |body| match body {
+            ResponseBody::Text(text) => Ok(text.into_bytes()),
+            ResponseBody::Bytes(bytes) => Ok(bytes),
+        },
+    }
+}
+
+/// Decodes a JSON body with serde
+pub fn expect_json<T: serde::de::DeserializeOwned>() -> Expect<T> {
+    Expect {
+        response_type: ResponseType::Text,
+        decode: decode_json::<T>,
+    }
+}
+
+fn decode_json<T: serde::de::DeserializeOwned>(body: ResponseBody) -> Result<T, String> {
+    match body {
+        ResponseBody::Text(text) => serde_json::from_str(&text),
+        ResponseBody::Bytes(bytes) => serde_json::from_slice(&bytes),
+    }
+    .map_err(|error| error.to_string())
+}
+
+pub struct Send<T, Msg> {
+    request: Request,
+    expect: Expect<T>,
+    to_msg: fn(Result<T, Error>) -> Msg,
+}
+
+impl<T: 'static, Msg: 'static> Cmd<Msg> for Send<T, Msg> {
+    fn run(&self, dispatcher: &Dispatcher<Msg>) -> Result<(), JsValue> {
+        let dispatcher = dispatcher.clone();
+        let decode = self.expect.decode;
+        let to_msg = self.to_msg;
+        let tracker = self.request.tracker.clone();
+        let id = track::request_id();
+        let finished = Rc::new(Cell::new(false));
+
+        let progress: Box<dyn Fn(Progress)> = match &tracker {
+            Some(tracker) => {
+                let tracker = tracker.clone();
+                Box::new(move |progress| track::report(&tracker, progress))
+            }
+            None => Box::new(|_| {}),
+        };
+        let done = finished.clone();
+        let done_tracker = tracker.clone();
+        let abort = backend().send(
+            &self.request,
+            self.expect.response_type,
+            progress,
+            Box::new(move |result| {
+                done.set(true);
+                if let Some(tracker) = &done_tracker {
+                    track::finish(tracker, id);
+                }
+                let result = result.and_then(|response| {
+                    if (200..300).contains(&response.status) {
+                        decode(response.body).map_err(Error::BadBody)
+                    } else {
+                        Err(Error::BadStatus(response.status))
+                    }
+                });
+                dispatcher.dispatch(to_msg(result));
+            }),
+        )?;
+        // Requests that failed right away are not in flight anymore
+        if let (Some(tracker), false) = (&tracker, finished.get()) {
+            track::start(tracker, id, abort);
+        }
+        Ok(())
+    }
+}
+
+/// How a backend should read the body of a response. Backends that can't choose may hand
+/// over either kind of body, as both can be decoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResponseType {
+    Text,
+    Bytes,
+}
+
+/// A response of any status, as a backend received it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: ResponseBody,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseBody {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+/// Sends the requests of the commands in this module. `XhrBackend` is the default, and
+/// tests without a browser use a `MockBackend` instead.
+pub trait Backend {
+    /// Sends `request`, and calls `done` once with the response, whatever its status, or
+    /// with the error when there is no response. `progress` is called as the body is
+    /// uploaded and the response downloaded, which only matters for tracked requests.
+    ///
+    /// `done` must not be called after the returned `Abort` is used.
+    fn send(
+        &self,
+        request: &Request,
+        response_type: ResponseType,
+        progress: Box<dyn Fn(Progress)>,
+        done: Box<dyn FnOnce(Result<Response, Error>)>,
+    ) -> Result<Abort, JsValue>;
+}
+
+/// Aborts a request a backend sent
+pub struct Abort(Box<dyn FnOnce()>);
+
+impl Abort {
+    pub fn new<F>(abort: F) -> Self
+    where
+        F: FnOnce() + 'static,
+    {
+        Abort(Box::new(abort))
+    }
+
+    pub fn abort(self) {
+        (self.0)()
+    }
+}
+
+thread_local! {
+    static BACKEND: RefCell<Rc<dyn Backend>> = RefCell::new(Rc::new(XhrBackend));
+}
+
+/// Sends the requests of every command that runs after this with `backend`
+pub fn set_backend<B: Backend + 'static>(backend: B) {
+    BACKEND.with(|current| *current.borrow_mut() = Rc::new(backend));
+}
+
+fn backend() -> Rc<dyn Backend> {
+    BACKEND.with(|backend| backend.borrow().clone())
+}
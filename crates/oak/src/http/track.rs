@@ -0,0 +1,281 @@
+use crate::http::{Abort, Request};
+use crate::platform::{Cmd, Dispatcher, Sub, Subs, Subscription};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::JsValue;
+
+/// How far a tracked request got
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Progress {
+    /// Bytes of the body that were uploaded, out of `size`
+    Sending { sent: u64, size: u64 },
+    /// Bytes of the response that were downloaded. The size is unknown when the server did
+    /// not send a `Content-Length`.
+    Receiving { received: u64, size: Option<u64> },
+}
+
+/// Tracks `request` as `tracker`, so that its progress shows up in the `progress`
+/// subscription of the same tracker, and `cancel` can abort it
+pub fn track(tracker: &str, request: Request) -> Request {
+    Request {
+        tracker: Some(tracker.to_owned()),
+        ..request
+    }
+}
+
+/// A subscription to the progress of the requests tracked as `tracker`
+pub fn progress<Msg>(tracker: &str, to_msg: fn(Progress) -> Msg) -> Tracking<Msg> {
+    Tracking {
+        tracker: tracker.to_owned(),
+        to_msg,
+    }
+}
+
+/// A command that aborts the requests tracked as `tracker` which are still in flight. Their
+/// commands don't send a message.
+pub fn cancel(tracker: &str) -> Cancel {
+    Cancel {
+        tracker: tracker.to_owned(),
+    }
+}
+
+pub struct Tracking<Msg> {
+    tracker: String,
+    to_msg: fn(Progress) -> Msg,
+}
+
+impl<Msg: 'static> Sub<Msg> for Tracking<Msg> {
+    fn collect(&self, subs: &mut Subs<Msg>) {
+        let tracker = self.tracker.clone();
+        let to_msg = self.to_msg;
+        subs.add(
+            format!("http::progress/{}/{:p}", tracker, to_msg),
+            move |dispatcher| {
+                let dispatcher = dispatcher.clone();
+                let id = TRACKERS.with(|trackers| {
+                    trackers.borrow_mut().listen(
+                        &tracker,
+                        Rc::new(move |progress| dispatcher.dispatch(to_msg(progress))),
+                    )
+                });
+                Ok(Subscription::new(move || {
+                    TRACKERS.with(|trackers| trackers.borrow_mut().unlisten(&tracker, id))
+                }))
+            },
+        );
+    }
+}
+
+pub struct Cancel {
+    tracker: String,
+}
+
+impl<Msg> Cmd<Msg> for Cancel {
+    fn run(&self, _: &Dispatcher<Msg>) -> Result<(), JsValue> {
+        let requests = TRACKERS.with(|trackers| {
+            trackers
+                .borrow_mut()
+                .requests
+                .remove(&self.tracker)
+                .unwrap_or_default()
+        });
+        for (_, abort) in requests {
+            abort.abort();
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static TRACKERS: RefCell<Trackers> = RefCell::new(Trackers::default());
+}
+
+type Listener = Rc<dyn Fn(Progress)>;
+
+/// The progress listeners and requests in flight of every tracker
+#[derive(Default)]
+struct Trackers {
+    next_id: usize,
+    listeners: HashMap<String, Vec<(usize, Listener)>>,
+    requests: HashMap<String, Vec<(usize, Abort)>>,
+}
+
+impl Trackers {
+    fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn listen(&mut self, tracker: &str, listener: Listener) -> usize {
+        let id = self.next_id();
+        self.listeners
+            .entry(tracker.to_owned())
+            .or_default()
+            .push((id, listener));
+        id
+    }
+
+    fn unlisten(&mut self, tracker: &str, id: usize) {
+        if let Some(listeners) = self.listeners.get_mut(tracker) {
+            listeners.retain(|(listener_id, _)| *listener_id != id);
+            if listeners.is_empty() {
+                self.listeners.remove(tracker);
+            }
+        }
+    }
+}
+
+/// A new id for a tracked request
+pub(crate) fn request_id() -> usize {
+    TRACKERS.with(|trackers| trackers.borrow_mut().next_id())
+}
+
+/// Keeps `abort` for `cancel` while the request is in flight
+pub(crate) fn start(tracker: &str, id: usize, abort: Abort) {
+    TRACKERS.with(|trackers| {
+        trackers
+            .borrow_mut()
+            .requests
+            .entry(tracker.to_owned())
+            .or_default()
+            .push((id, abort))
+    });
+}
+
+/// Forgets a request that is done
+pub(crate) fn finish(tracker: &str, id: usize) {
+    // The abort is dropped once the registry is not borrowed anymore
+    let _abort = TRACKERS.with(|trackers| {
+        let mut trackers = trackers.borrow_mut();
+        let requests = trackers.requests.get_mut(tracker)?;
+        let index = requests
+            .iter()
+            .position(|(request_id, _)| *request_id == id)?;
+        let abort = requests.remove(index);
+        if requests.is_empty() {
+            trackers.requests.remove(tracker);
+        }
+        Some(abort)
+    });
+}
+
+/// Hands `progress` to the subscriptions of `tracker`
+pub(crate) fn report(tracker: &str, progress: Progress) {
+    // Listeners are called without borrowing the registry, as their messages may cancel
+    // requests or end subscriptions
+    let listeners = TRACKERS.with(|trackers| {
+        trackers
+            .borrow()
+            .listeners
+            .get(tracker)
+            .cloned()
+            .unwrap_or_default()
+    });
+    for (_, listener) in listeners {
+        listener(progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{self, Body, Error, MockBackend};
+    use std::time::Duration;
+
+    type Messages<T> = Rc<RefCell<Vec<T>>>;
+
+    fn recorder<T: 'static>() -> (Dispatcher<T>, Messages<T>) {
+        let messages = Messages::default();
+        let received = messages.clone();
+        let dispatcher = Dispatcher::new(move |message| received.borrow_mut().push(message));
+        (dispatcher, messages)
+    }
+
+    fn upload(tracker: Option<&str>, dispatcher: &Dispatcher<Result<String, Error>>) {
+        let request = http::post("/upload", Body::text("text/plain", "abc"));
+        let request = match tracker {
+            Some(tracker) => track(tracker, request),
+            None => request,
+        };
+        request
+            .send(http::expect_text(), |result| result)
+            .run(dispatcher)
+            .unwrap();
+    }
+
+    /// How many trackers have listeners, and how many have requests in flight
+    fn registry() -> (usize, usize) {
+        TRACKERS.with(|trackers| {
+            let trackers = trackers.borrow();
+            (trackers.listeners.len(), trackers.requests.len())
+        })
+    }
+
+    #[test]
+    fn reports_the_progress_of_tracked_requests() {
+        let mock = MockBackend::new();
+        mock.on("POST", "/upload").respond(200, "done");
+        http::set_backend(mock.clone());
+        let (progress_dispatcher, progress_messages) = recorder();
+        let mut subs = Subs::new();
+        progress("upload", |progress| progress).collect(&mut subs);
+        let _subscriptions = subs.start(&progress_dispatcher).unwrap();
+
+        let (dispatcher, messages) = recorder();
+        upload(None, &dispatcher);
+        mock.flush();
+        assert!(progress_messages.borrow().is_empty());
+
+        upload(Some("upload"), &dispatcher);
+        mock.flush();
+        assert_eq!(
+            *progress_messages.borrow(),
+            vec![
+                Progress::Sending { sent: 3, size: 3 },
+                Progress::Receiving {
+                    received: 4,
+                    size: Some(4)
+                },
+            ]
+        );
+        assert_eq!(messages.borrow().len(), 2);
+    }
+
+    #[test]
+    fn cancel_aborts_pending_requests_without_a_message() {
+        let mock = MockBackend::new();
+        mock.on("POST", "/upload")
+            .delay(Duration::from_secs(1))
+            .respond(200, "done");
+        http::set_backend(mock.clone());
+        let (dispatcher, messages) = recorder();
+        upload(Some("upload"), &dispatcher);
+        upload(Some("other"), &dispatcher);
+
+        cancel("upload").run(&dispatcher).unwrap();
+        assert_eq!(mock.pending(), 1);
+        mock.flush();
+        assert_eq!(*messages.borrow(), vec![Ok("done".to_owned())]);
+        assert_eq!(registry(), (0, 0));
+    }
+
+    #[test]
+    fn forgets_finished_requests_and_ended_subscriptions() {
+        let mock = MockBackend::new();
+        mock.on("POST", "/upload").respond(200, "done");
+        http::set_backend(mock.clone());
+        let (progress_dispatcher, _) = recorder();
+        let mut subs = Subs::new();
+        progress("upload", |progress| progress).collect(&mut subs);
+        let subscriptions = subs.start(&progress_dispatcher).unwrap();
+        let (dispatcher, _) = recorder();
+        upload(Some("upload"), &dispatcher);
+        assert_eq!(registry(), (1, 1));
+
+        mock.flush();
+        assert_eq!(registry(), (1, 0));
+        drop(subscriptions);
+        assert_eq!(registry(), (0, 0));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::http::{
+    Abort, Backend, Body, Error, Progress, Request, Response, ResponseBody, ResponseType,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::{Closure, JsValue};
+use wasm_bindgen::JsCast;
+
+/// The backend of the browser, which sends requests with `XMLHttpRequest`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct XhrBackend;
+
+impl Backend for XhrBackend {
+    fn send(
+        &self,
+        request: &Request,
+        response_type: ResponseType,
+        progress: Box<dyn Fn(Progress)>,
+        done: Box<dyn FnOnce(Result<Response, Error>)>,
+    ) -> Result<Abort, JsValue> {
+        let xhr = web_sys::XmlHttpRequest::new()?;
+        if xhr
+            .open_with_async(&request.method, &request.url, true)
+            .is_err()
+        {
+            done(Err(Error::BadUrl(request.url.clone())));
+            return Ok(Abort::new(|| {}));
+        }
+        let has_content_type = request
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+        if let (Some(content_type), false) = (request.body.content_type(), has_content_type) {
+            xhr.set_request_header("Content-Type", content_type)?;
+        }
+        for (name, value) in &request.headers {
+            xhr.set_request_header(name, value)?;
+        }
+        if let Some(timeout) = request.timeout {
+            xhr.set_timeout(timeout.as_millis() as u32);
+        }
+        xhr.set_with_credentials(request.with_credentials);
+        xhr.set_response_type(match response_type {
+            ResponseType::Text => web_sys::XmlHttpRequestResponseType::Text,
+            ResponseType::Bytes => web_sys::XmlHttpRequestResponseType::Arraybuffer,
+        });
+
+        // One listener handles however the request ends, and drops every listener when it
+        // does
+        let listeners = Rc::new(RefCell::new(vec![]));
+        let mut done = Some(done);
+        let target = xhr.clone();
+        let own_listeners = listeners.clone();
+        let closure = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            let done = match done.take() {
+                Some(done) => done,
+                None => return,
+            };
+            let result = match event.type_().as_str() {
+                "timeout" => Err(Error::Timeout),
+                "error" => Err(Error::NetworkError),
+                _ => read_response(&target, response_type),
+            };
+            own_listeners.borrow_mut().clear();
+            done(result);
+        }) as Box<dyn FnMut(web_sys::Event)>);
+        for event in &["load", "error", "timeout"] {
+            xhr.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())?;
+        }
+        listeners.borrow_mut().push(closure);
+
+        // Listening to uploads makes cross-origin requests preflighted, so only tracked
+        // requests do
+        if request.tracker.is_some() {
+            let progress: Rc<dyn Fn(Progress)> = progress.into();
+            let upload_progress = progress.clone();
+            let upload = Closure::wrap(Box::new(move |event: web_sys::Event| {
+                let event = event.unchecked_ref::<web_sys::ProgressEvent>();
+                upload_progress(Progress::Sending {
+                    sent: event.loaded() as u64,
+                    size: event.total() as u64,
+                });
+            }) as Box<dyn FnMut(web_sys::Event)>);
+            xhr.upload()?
+                .add_event_listener_with_callback("progress", upload.as_ref().unchecked_ref())?;
+            let download = Closure::wrap(Box::new(move |event: web_sys::Event| {
+                let event = event.unchecked_ref::<web_sys::ProgressEvent>();
+                progress(Progress::Receiving {
+                    received: event.loaded() as u64,
+                    size: if event.length_computable() {
+                        Some(event.total() as u64)
+                    } else {
+                        None
+                    },
+                });
+            }) as Box<dyn FnMut(web_sys::Event)>);
+            xhr.add_event_listener_with_callback("progress", download.as_ref().unchecked_ref())?;
+            let mut listeners = listeners.borrow_mut();
+            listeners.push(upload);
+            listeners.push(download);
+        }
+
+        match &request.body {
+            Body::Empty => xhr.send(),
+            Body::Text { content, .. } => xhr.send_with_opt_str(Some(content)),
+            Body::Bytes { content, .. } => xhr.send_with_opt_u8_array(Some(content)),
+        }?;
+        Ok(Abort::new(move || {
+            // An aborted request only fires `abort`, which nothing listens to
+            let _ = xhr.abort();
+            listeners.borrow_mut().clear();
+        }))
+    }
+}
+
+fn read_response(
+    xhr: &web_sys::XmlHttpRequest,
+    response_type: ResponseType,
+) -> Result<Response, Error> {
+    let status = xhr.status().map_err(|_| Error::NetworkError)?;
+    let response = xhr.response().map_err(|_| Error::NetworkError)?;
+    let body = match response_type {
+        ResponseType::Text => ResponseBody::Text(response.as_string().unwrap_or_default()),
+        ResponseType::Bytes => ResponseBody::Bytes(js_sys::Uint8Array::new(&response).to_vec()),
+    };
+    Ok(Response { status, body })
+}
@@ -1,11 +1,32 @@
+#[cfg(feature = "browser")]
 mod program;
+#[cfg(feature = "browser")]
 mod render;
+#[cfg(feature = "browser")]
+mod scheduler;
+#[cfg(feature = "browser")]
+mod subscriptions;
 
+#[cfg(feature = "browser")]
 pub mod browser;
 pub mod html;
+#[cfg(feature = "browser")]
+pub mod http;
+#[cfg(feature = "js")]
 pub mod platform;
 pub mod prelude;
+pub mod ssg;
+pub mod ssr;
+#[cfg(feature = "browser")]
+pub mod sse;
+pub mod svg;
+#[cfg(feature = "browser")]
 pub mod time;
+#[cfg(feature = "browser")]
+pub mod websocket;
 
-pub use self::platform::{Cmd, Sub};
+#[cfg(feature = "js")]
+pub use self::platform::{Cmd, Dispatcher, Sub, Subscription};
+#[cfg(feature = "browser")]
 pub use self::program::{element, sandbox};
+pub use oak_macros::html;
@@ -1,7 +1,82 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::rc::Rc;
 use wasm_bindgen::JsValue;
 
+/// A handle that feeds messages back into a running program.
+///
+/// Commands and subscriptions receive a dispatcher so that they can report their results
+/// as messages, the same way a DOM event does.
+pub struct Dispatcher<Msg> {
+    dispatch: Rc<dyn Fn(Msg)>,
+    dispatch_sync: Rc<dyn Fn(Msg)>,
+}
+
+impl<Msg: 'static> Dispatcher<Msg> {
+    pub fn new<F>(dispatch: F) -> Self
+    where
+        F: Fn(Msg) + 'static,
+    {
+        let dispatch = Rc::new(dispatch);
+        Dispatcher {
+            dispatch: dispatch.clone(),
+            dispatch_sync: dispatch,
+        }
+    }
+
+    /// A dispatcher that handles messages sent with `dispatch_sync` differently, usually by
+    /// rendering right away.
+    pub fn with_sync<F, G>(dispatch: F, dispatch_sync: G) -> Self
+    where
+        F: Fn(Msg) + 'static,
+        G: Fn(Msg) + 'static,
+    {
+        Dispatcher {
+            dispatch: Rc::new(dispatch),
+            dispatch_sync: Rc::new(dispatch_sync),
+        }
+    }
+
+    /// A dispatcher for `Child` messages, which are turned into messages of this one by `f`
+    pub fn map<Child, F>(&self, f: F) -> Dispatcher<Child>
+    where
+        F: Fn(Child) -> Msg + 'static,
+    {
+        let f = Rc::new(f);
+        let dispatch = self.dispatch.clone();
+        let dispatch_sync = self.dispatch_sync.clone();
+        let sync_f = f.clone();
+        Dispatcher {
+            dispatch: Rc::new(move |message| dispatch(f(message))),
+            dispatch_sync: Rc::new(move |message| dispatch_sync(sync_f(message))),
+        }
+    }
+}
+
+impl<Msg> Dispatcher<Msg> {
+    pub fn dispatch(&self, message: Msg) {
+        (self.dispatch)(message)
+    }
+
+    /// Like `dispatch`, but the view is rendered before this returns. This is meant for
+    /// inputs whose value must not lag behind what the user typed.
+    pub fn dispatch_sync(&self, message: Msg) {
+        (self.dispatch_sync)(message)
+    }
+}
+
+impl<Msg> Clone for Dispatcher<Msg> {
+    fn clone(&self) -> Self {
+        Dispatcher {
+            dispatch: self.dispatch.clone(),
+            dispatch_sync: self.dispatch_sync.clone(),
+        }
+    }
+}
+
 pub trait Cmd<Msg> {
-    fn run(&self) -> Result<(), JsValue>;
+    fn run(&self, dispatcher: &Dispatcher<Msg>) -> Result<(), JsValue>;
 
     fn boxed(self) -> Box<Self>
     where
@@ -9,23 +84,281 @@ pub trait Cmd<Msg> {
     {
         Box::new(self)
     }
+
+    /// Turns the messages of this command into `Parent` messages, so the command of a child
+    /// component can be returned from the update of its parent.
+    fn map<Parent, F>(self, f: F) -> Map<Self, F, Msg>
+    where
+        Self: Sized,
+        F: Fn(Msg) -> Parent + 'static,
+    {
+        Map::new(self, f)
+    }
 }
 
 pub trait Sub<Msg> {
-    fn init(&self) -> Result<(), JsValue>;
+    /// Adds every individual subscription this value stands for to `subs`.
+    fn collect(&self, subs: &mut Subs<Msg>);
+
+    /// Turns the messages of this subscription into `Parent` messages. A running
+    /// subscription always goes through the `f` of the latest update, so `f` may capture
+    /// values that change. Subscriptions mapped by the same closure, like one for every item
+    /// of a list, are told apart by their order.
+    fn map<Parent, F>(self, f: F) -> Map<Self, F, Msg>
+    where
+        Self: Sized,
+        F: Fn(Msg) -> Parent + 'static,
+    {
+        Map::new(self, f)
+    }
+
+    /// Like `map`, for a closure that maps several subscriptions, like one for every item of
+    /// a list. These are told apart by `key` instead of their order, so an item keeps its
+    /// subscriptions when the list is reordered.
+    fn map_keyed<Parent, F>(self, key: &str, f: F) -> Map<Self, F, Msg>
+    where
+        Self: Sized,
+        F: Fn(Msg) -> Parent + 'static,
+    {
+        Map {
+            key: Some(key.to_owned()),
+            ..Map::new(self, f)
+        }
+    }
+}
+
+type Start<Msg> = Box<dyn FnOnce(&Dispatcher<Msg>) -> Result<Subscription, JsValue>>;
+
+/// The set of subscriptions that a program should be listening to after an update.
+///
+/// Every subscription is identified by a key. When the key shows up again after the next
+/// update the running subscription is kept, otherwise it is torn down, and new keys are
+/// started with their start function.
+pub struct Subs<Msg> {
+    entries: Vec<Entry<Msg>>,
 }
 
+pub(crate) struct Entry<Msg> {
+    pub(crate) key: String,
+    pub(crate) start: Start<Msg>,
+    /// The mappers the subscription is mapped by, innermost first. These take the place of
+    /// the mappers of a running subscription with the same key.
+    pub(crate) mappers: Vec<Rc<dyn Remap>>,
+}
+
+impl<Msg> Subs<Msg> {
+    pub fn new() -> Self {
+        Subs { entries: vec![] }
+    }
+
+    pub fn add<F>(&mut self, key: String, start: F)
+    where
+        F: FnOnce(&Dispatcher<Msg>) -> Result<Subscription, JsValue> + 'static,
+    {
+        self.entries.push(Entry {
+            key,
+            start: Box::new(start),
+            mappers: vec![],
+        });
+    }
+
+    /// Starts every subscription with `dispatcher`. Programs only start the ones that are
+    /// not running yet, this is for running subscriptions outside of a program, like in
+    /// tests.
+    pub fn start(self, dispatcher: &Dispatcher<Msg>) -> Result<Vec<Subscription>, JsValue> {
+        self.entries
+            .into_iter()
+            .map(|entry| (entry.start)(dispatcher))
+            .collect()
+    }
+
+    pub(crate) fn into_entries(self) -> Vec<Entry<Msg>> {
+        self.entries
+    }
+}
+
+impl<Msg> Default for Subs<Msg> {
+    fn default() -> Self {
+        Subs::new()
+    }
+}
+
+/// A running subscription. Dropping it tears the subscription down.
+pub struct Subscription(Option<Box<dyn FnOnce()>>);
+
+impl Subscription {
+    pub fn new<F>(cancel: F) -> Self
+    where
+        F: FnOnce() + 'static,
+    {
+        Subscription(Some(Box::new(cancel)))
+    }
+
+    /// A subscription that has nothing to tear down.
+    pub fn none() -> Self {
+        Subscription(Option::None)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.0.take() {
+            cancel();
+        }
+    }
+}
 
 pub struct None;
 
 impl<Msg> Cmd<Msg> for None {
-    fn run(&self) -> Result<(), JsValue> {
+    fn run(&self, _: &Dispatcher<Msg>) -> Result<(), JsValue> {
         Ok(())
     }
 }
 
 impl<Msg> Sub<Msg> for None {
-    fn init(&self) -> Result<(), JsValue> {
+    fn collect(&self, _: &mut Subs<Msg>) {}
+}
+
+impl<Msg> Cmd<Msg> for Box<dyn Cmd<Msg>> {
+    fn run(&self, dispatcher: &Dispatcher<Msg>) -> Result<(), JsValue> {
+        (**self).run(dispatcher)
+    }
+}
+
+impl<Msg> Sub<Msg> for Box<dyn Sub<Msg>> {
+    fn collect(&self, subs: &mut Subs<Msg>) {
+        (**self).collect(subs)
+    }
+}
+
+/// Several commands or subscriptions combined into one.
+pub struct Batch<T>(pub Vec<T>);
+
+pub fn batch<T>(items: Vec<T>) -> Batch<T> {
+    Batch(items)
+}
+
+impl<Msg, C: Cmd<Msg>> Cmd<Msg> for Batch<C> {
+    fn run(&self, dispatcher: &Dispatcher<Msg>) -> Result<(), JsValue> {
+        for cmd in &self.0 {
+            cmd.run(dispatcher)?;
+        }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl<Msg, S: Sub<Msg>> Sub<Msg> for Batch<S> {
+    fn collect(&self, subs: &mut Subs<Msg>) {
+        for sub in &self.0 {
+            sub.collect(subs);
+        }
+    }
+}
+
+/// A command or subscription whose messages are mapped by `F`. Created by `Cmd::map` and
+/// `Sub::map`.
+pub struct Map<T, F, Msg> {
+    inner: T,
+    mapper: Rc<F>,
+    /// Tells subscriptions with the same type of mapper apart
+    key: Option<String>,
+    message: PhantomData<fn(Msg)>,
+}
+
+impl<T, F, Msg> Map<T, F, Msg> {
+    fn new(inner: T, mapper: F) -> Self {
+        Map {
+            inner,
+            mapper: Rc::new(mapper),
+            key: Option::None,
+            message: PhantomData,
+        }
+    }
+}
+
+impl<T, F, Msg, Parent> Cmd<Parent> for Map<T, F, Msg>
+where
+    T: Cmd<Msg>,
+    F: Fn(Msg) -> Parent + 'static,
+    Msg: 'static,
+    Parent: 'static,
+{
+    fn run(&self, dispatcher: &Dispatcher<Parent>) -> Result<(), JsValue> {
+        let mapper = self.mapper.clone();
+        self.inner
+            .run(&dispatcher.map(move |message| mapper(message)))
+    }
+}
+
+impl<T, F, Msg, Parent> Sub<Parent> for Map<T, F, Msg>
+where
+    T: Sub<Msg>,
+    F: Fn(Msg) -> Parent + 'static,
+    Msg: 'static,
+    Parent: 'static,
+{
+    fn collect(&self, subs: &mut Subs<Parent>) {
+        let mut inner = Subs::new();
+        self.inner.collect(&mut inner);
+        for entry in inner.into_entries() {
+            // The mapper is part of the key, so the same subscription mapped differently is
+            // a different subscription. Every closure has a type of its own, but not
+            // necessarily a name of its own.
+            let key = format!(
+                "map/{:?}/{}/{}",
+                TypeId::of::<F>(),
+                self.key.as_deref().unwrap_or(""),
+                entry.key
+            );
+            // The same closure may map the same subscription several times, with different
+            // captured values
+            let mut unique = key.clone();
+            let mut count = 1;
+            while subs.entries.iter().any(|entry| entry.key == unique) {
+                count += 1;
+                unique = format!("{}#{}", key, count);
+            }
+
+            let mapper: Rc<dyn Fn(Msg) -> Parent> = self.mapper.clone();
+            let slot = Rc::new(Mapper(RefCell::new(mapper)));
+            let mut mappers = entry.mappers;
+            mappers.push(slot.clone());
+            let start = entry.start;
+            subs.entries.push(Entry {
+                key: unique,
+                start: Box::new(move |dispatcher| {
+                    start(&dispatcher.map(move |message| {
+                        // Not borrowed while dispatching, which may update the mapper
+                        let mapper = slot.0.borrow().clone();
+                        mapper(message)
+                    }))
+                }),
+                mappers,
+            });
+        }
+    }
+}
+
+/// A mapper of a running subscription, which the next update may replace
+pub(crate) trait Remap {
+    fn as_any(&self) -> &dyn Any;
+
+    /// Replaces this mapper with `latest`, the one in the same place after an update
+    fn remap(&self, latest: &dyn Remap);
+}
+
+struct Mapper<Msg, Parent>(RefCell<Rc<dyn Fn(Msg) -> Parent>>);
+
+impl<Msg: 'static, Parent: 'static> Remap for Mapper<Msg, Parent> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn remap(&self, latest: &dyn Remap) {
+        if let Some(latest) = latest.as_any().downcast_ref::<Self>() {
+            let mapper = latest.0.borrow().clone();
+            self.0.replace(mapper);
+        }
+    }
+}
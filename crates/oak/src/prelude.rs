@@ -1,8 +1,8 @@
-pub use crate::{
-    html::attributes::*,
-    html::events::*,
-    html::*,
-    platform::{self, *},
-    time::Time,
-};
+pub use crate::{html, html::attributes::*, html::events::*, html::*};
+
+#[cfg(feature = "js")]
+pub use crate::platform::{self, *};
+#[cfg(feature = "browser")]
+pub use crate::time::Time;
+#[cfg(feature = "js")]
 pub use wasm_bindgen::prelude::*;
@@ -1,58 +1,177 @@
-use crate::{browser::Resources, html::Html, platform, render, Cmd, Sub};
+use crate::{
+    browser::Resources,
+    html::Html,
+    platform::{self, Dispatcher},
+    render,
+    scheduler::RenderScheduler,
+    subscriptions::SubscriptionManager,
+    Cmd, Sub,
+};
 use std::cell::RefCell;
 use std::fmt::Debug;
 use std::rc::Rc;
 use wasm_bindgen::{JsCast, JsValue};
 
+/// Returns the subscriptions a program should be listening to for a model
+type Subscriptions<Model, Msg> = dyn Fn(&Model) -> Box<dyn Sub<Msg>>;
+
 pub struct Program<Model, Msg> {
     pub model: RefCell<Model>,
     pub view: Box<Fn(&Model) -> Html<Msg>>,
     pub update: Box<Fn(&Msg, &mut Model) -> Box<Cmd<Msg>>>,
+    pub subscriptions: Box<Subscriptions<Model, Msg>>,
+    pub subscription_manager: SubscriptionManager,
+    pub init_cmd: RefCell<Option<Box<dyn Cmd<Msg>>>>,
     pub last_tree: RefCell<Option<Html<Msg>>>,
+    pub scheduler: RenderScheduler,
     pub browser: Resources,
     pub root: web_sys::Node,
 }
 
 impl<Model, Msg> Program<Model, Msg>
 where
-    Model: Debug + Clone + 'static,
+    Model: Debug + 'static,
     Msg: PartialEq + Debug + Clone + 'static,
 {
-    fn new<InitFn, ViewFn, UpdateFn>(init: InitFn, view: ViewFn, update: UpdateFn) -> Self
+    fn new<InitFn, ViewFn, UpdateFn, SubscriptionsFn>(
+        init: InitFn,
+        view: ViewFn,
+        update: UpdateFn,
+        subscriptions: SubscriptionsFn,
+    ) -> Self
     where
         InitFn: FnOnce() -> (Model, Box<Cmd<Msg>>),
         ViewFn: Fn(&Model) -> Html<Msg> + 'static,
         UpdateFn: Fn(&Msg, &mut Model) -> Box<Cmd<Msg>> + 'static,
+        SubscriptionsFn: Fn(&Model) -> Box<dyn Sub<Msg>> + 'static,
     {
-        let (model, _) = init();
+        let (model, init_cmd) = init();
         let browser = Resources::new().unwrap();
         let root = browser.document.create_document_fragment();
         Self {
             model: RefCell::new(model),
             view: Box::new(view),
             update: Box::new(update),
+            subscriptions: Box::new(subscriptions),
+            subscription_manager: SubscriptionManager::new(),
+            init_cmd: RefCell::new(Some(init_cmd)),
             last_tree: RefCell::new(None),
+            scheduler: RenderScheduler::new(),
             browser,
             root: root.dyn_into().unwrap(),
         }
     }
 
+    /// Updates the model with `message`. The view is rendered on the next animation frame,
+    /// together with any other message that arrives before then.
     pub fn dispatch(program: &Rc<Self>, message: &Msg) -> Result<(), JsValue> {
-        let mut model = program.model.borrow().clone();
-        let cmd = (program.update)(message, &mut model);
-        program.model.replace(model);
+        let cmd = Program::update(program, message);
+        Program::schedule_render(program)?;
+        Program::update_subscriptions(program);
+        cmd.run(&Program::dispatcher(program))
+    }
+
+    /// Like `dispatch`, but renders right away. This is meant for inputs whose value must not
+    /// lag behind what the user typed.
+    pub fn dispatch_sync(program: &Rc<Self>, message: &Msg) -> Result<(), JsValue> {
+        let cmd = Program::update(program, message);
         Program::render(program)?;
-        cmd.run()
+        Program::update_subscriptions(program);
+        cmd.run(&Program::dispatcher(program))
+    }
+
+    /// Runs `update` against the model in place. The borrow ends before rendering and running
+    /// commands, which may need to read the model or dispatch again.
+    fn update(program: &Rc<Self>, message: &Msg) -> Box<dyn Cmd<Msg>> {
+        let mut model = program.model.borrow_mut();
+        (program.update)(message, &mut model)
+    }
+
+    fn schedule_render(program: &Rc<Self>) -> Result<(), JsValue> {
+        let frame_program = program.clone();
+        program
+            .scheduler
+            .schedule(&program.browser.window, move || {
+                if frame_program.scheduler.frame() {
+                    if let Err(error) = Program::render(&frame_program) {
+                        log::error!("{:#?}", error);
+                    }
+                }
+            })
+    }
+
+    /// Starts and stops subscriptions to match the model. A subscription that fails to start
+    /// is logged instead of returned, so that the command of the same update still runs.
+    fn update_subscriptions(program: &Rc<Self>) {
+        let sub = (program.subscriptions)(&program.model.borrow());
+        let result = program
+            .subscription_manager
+            .update(&*sub, &Program::dispatcher(program));
+        if let Err(error) = result {
+            log::error!("{:#?}", error);
+        }
+    }
+
+    /// Creates a dispatcher that sends messages produced by commands, subscriptions and
+    /// events back through `Program::dispatch`, or `Program::dispatch_sync`.
+    pub fn dispatcher(program: &Rc<Self>) -> Dispatcher<Msg> {
+        let sync_program = program.clone();
+        let program = program.clone();
+        Dispatcher::with_sync(
+            move |message| {
+                if let Err(error) = Program::dispatch(&program, &message) {
+                    log::error!("{:#?}", error);
+                }
+            },
+            move |message| {
+                if let Err(error) = Program::dispatch_sync(&sync_program, &message) {
+                    log::error!("{:#?}", error);
+                }
+            },
+        )
     }
 
     fn render(program: &Rc<Self>) -> Result<(), JsValue> {
         let tree = (program.view)(&program.model.borrow());
-        render::Renderer::render(&program.root, program, &tree, &program.last_tree.borrow())?;
+        render::Renderer::render(
+            &program.root,
+            &Program::dispatcher(program),
+            &program.browser.document,
+            &tree,
+            &program.last_tree.borrow(),
+        )?;
         program.last_tree.replace(Some(tree));
+        program.scheduler.rendered();
         Ok(())
     }
 
-    pub fn init(mut self, selector: &str) -> Result<(), JsValue> {
+    /// Adopts the markup under the root that was rendered on the server from the same view,
+    /// instead of rendering it again. Event listeners are attached to the existing nodes.
+    fn hydrate_view(program: &Rc<Self>) -> Result<(), JsValue> {
+        let tree = (program.view)(&program.model.borrow());
+        render::Renderer::hydrate(
+            &program.root,
+            &Program::dispatcher(program),
+            &program.browser.document,
+            &tree,
+        )?;
+        program.last_tree.replace(Some(tree));
+        program.scheduler.rendered();
+        Ok(())
+    }
+
+    /// Mounts the program on the element matching `selector`, rendering the view into it.
+    pub fn init(self, selector: &str) -> Result<(), JsValue> {
+        self.mount(selector, false)
+    }
+
+    /// Mounts the program on the element matching `selector`, which already contains the
+    /// server-rendered markup of the initial view.
+    pub fn hydrate(self, selector: &str) -> Result<(), JsValue> {
+        self.mount(selector, true)
+    }
+
+    fn mount(mut self, selector: &str, hydrate: bool) -> Result<(), JsValue> {
         console_error_panic_hook::set_once();
         wasm_logger::init(wasm_logger::Config::new(log::Level::Debug));
         self.root = self
@@ -63,7 +182,19 @@ where
             .expect("did not find element")
             .dyn_into()?;
         let program = Rc::new(self);
-        Program::render(&program)
+        if hydrate {
+            Program::hydrate_view(&program)?;
+        } else {
+            Program::render(&program)?;
+        }
+        Program::update_subscriptions(&program);
+        // The initial command runs once the program is mounted, so it can already rely on
+        // the DOM produced by the first render
+        let init_cmd = program.init_cmd.borrow_mut().take();
+        if let Some(cmd) = init_cmd {
+            cmd.run(&Program::dispatcher(&program))?;
+        }
+        Ok(())
     }
 }
 
@@ -73,7 +204,7 @@ pub fn sandbox<Model, Msg, ViewFn, UpdateFn>(
     update: UpdateFn,
 ) -> Program<Model, Msg>
 where
-    Model: Debug + Clone + 'static,
+    Model: Debug + 'static,
     Msg: PartialEq + Debug + Clone + 'static,
     ViewFn: Fn(&Model) -> Html<Msg> + 'static,
     UpdateFn: Fn(&Msg, &mut Model) + 'static,
@@ -85,6 +216,7 @@ where
             update(msg, model);
             Box::new(platform::None)
         },
+        |_| Box::new(platform::None),
     )
 }
 
@@ -105,7 +237,7 @@ pub fn element<
     subscriptions: SubscriptionsFn,
 ) -> Program<Model, Msg>
 where
-    Model: Debug + Clone + 'static,
+    Model: Debug + 'static,
     Msg: PartialEq + Debug + Clone + 'static,
     InitCmd: Cmd<Msg> + 'static,
     InitFn: FnOnce() -> (Model, InitCmd),
@@ -113,7 +245,7 @@ where
     UpdateCmd: Cmd<Msg> + 'static,
     UpdateFn: Fn(&Msg, &mut Model) -> UpdateCmd + 'static,
     SubscriptionsSub: Sub<Msg> + 'static,
-    SubscriptionsFn: Fn(&Model) -> SubscriptionsSub,
+    SubscriptionsFn: Fn(&Model) -> SubscriptionsSub + 'static,
 {
     Program::new(
         move || {
@@ -122,5 +254,6 @@ where
         },
         view,
         move |msg, model| Box::new(update(msg, model)),
+        move |model| Box::new(subscriptions(model)),
     )
 }
@@ -1,18 +1,32 @@
 use crate::{
-    html::{Attribute, Children, Element, EventListener, EventToMessage, Html},
-    program::Program,
+    html::{Attribute, Children, Element, EventListener, Html, Value},
+    platform::Dispatcher,
 };
 use itertools::{EitherOrBoth, Itertools};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
-pub struct Renderer<Model, Msg> {
-    program: Rc<Program<Model, Msg>>,
+pub struct Renderer<Msg> {
+    dispatcher: Dispatcher<Msg>,
+    document: web_sys::Document,
     to_remove: Vec<(web_sys::Node, web_sys::Node)>,
 }
 
+/// What the renderer keeps in the `JsClosure` of an event listener
+struct Listener<Msg> {
+    closure: Closure<dyn Fn(web_sys::Event)>,
+    /// Replaced whenever the listener is reused, so that it dispatches through the mappers of
+    /// the latest view
+    dispatcher: Rc<RefCell<Dispatcher<Msg>>>,
+    /// Replaced whenever the listener is reused too, see `update_listeners`
+    handler: Rc<RefCell<EventListener<Msg>>>,
+}
+
 fn eiter_or_both_to_option_tuple<T>(pair: EitherOrBoth<T, T>) -> (Option<T>, Option<T>) {
     use itertools::EitherOrBoth::{Both, Left, Right};
     match pair {
@@ -22,25 +36,197 @@ fn eiter_or_both_to_option_tuple<T>(pair: EitherOrBoth<T, T>) -> (Option<T>, Opt
     }
 }
 
-impl<Model, Msg> Renderer<Model, Msg>
+impl<Msg> Renderer<Msg>
 where
     Msg: PartialEq + Debug + Clone + 'static,
-    Model: Debug + Clone + 'static,
 {
     pub fn render(
         root: &web_sys::Node,
-        program: &Rc<Program<Model, Msg>>,
+        dispatcher: &Dispatcher<Msg>,
+        document: &web_sys::Document,
         new_tree: &Html<Msg>,
         old_tree: &Option<Html<Msg>>,
     ) -> Result<(), JsValue> {
-        let mut renderer = Renderer {
-            program: program.clone(),
+        let mut renderer = Renderer::new(dispatcher.clone(), document.clone());
+        // The tree may be a fragment, so the root is diffed like a list of children
+        let old_children = old_tree.as_ref().map(std::slice::from_ref).unwrap_or(&[]);
+        renderer.update_children(root, old_children, std::slice::from_ref(new_tree))?;
+        renderer.finish()
+    }
+
+    fn new(dispatcher: Dispatcher<Msg>, document: web_sys::Document) -> Self {
+        Renderer {
+            dispatcher,
+            document,
             to_remove: vec![],
+        }
+    }
+
+    /// A renderer for a view with `Child` messages, which are mapped by `mapper`
+    pub(crate) fn map<Child>(&self, mapper: Rc<dyn Fn(Child) -> Msg>) -> Renderer<Child>
+    where
+        Child: PartialEq + Debug + Clone + 'static,
+    {
+        Renderer::new(
+            self.dispatcher.map(move |message| mapper(message)),
+            self.document.clone(),
+        )
+    }
+
+    /// Removes the nodes that were left for removal until all indexes were used
+    pub(crate) fn finish(self) -> Result<(), JsValue> {
+        for (parent, child) in &self.to_remove {
+            parent.remove_child(child)?;
+        }
+        Ok(())
+    }
+
+    /// Adopts the markup already under `root`, which was rendered from `tree` on the server,
+    /// instead of creating new nodes. Event listeners are attached to the existing elements,
+    /// and wherever the markup does not match the tree it is repaired and logged.
+    pub fn hydrate(
+        root: &web_sys::Node,
+        dispatcher: &Dispatcher<Msg>,
+        document: &web_sys::Document,
+        tree: &Html<Msg>,
+    ) -> Result<(), JsValue> {
+        let renderer = Renderer::new(dispatcher.clone(), document.clone());
+        renderer.hydrate_children(root, std::slice::from_ref(tree))
+    }
+
+    fn hydrate_children(
+        &self,
+        parent: &web_sys::Node,
+        children: &[Html<Msg>],
+    ) -> Result<(), JsValue> {
+        let children = flatten(children);
+        for (index, child) in children.iter().enumerate() {
+            let node = parent.child_nodes().item(index as u32);
+            self.hydrate_node(parent, node, child)?;
+        }
+        // Later renders find nodes by index, so nothing the view does not know about can stay
+        while let Some(extra) = parent.child_nodes().item(children.len() as u32) {
+            log::warn!(
+                "Hydration mismatch: removing unexpected {} node",
+                extra.node_name()
+            );
+            parent.remove_child(&extra)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn hydrate_node(
+        &self,
+        parent: &web_sys::Node,
+        node: Option<web_sys::Node>,
+        html: &Html<Msg>,
+    ) -> Result<(), JsValue> {
+        let node = match node {
+            Some(node) => node,
+            None => {
+                log::warn!("Hydration mismatch: creating missing {}", describe(html));
+                parent.append_child(&self.create_node(html)?)?;
+                return Ok(());
+            }
         };
-        // TODO: We should probably not assume that the number here is 0
-        renderer.update_element(root, Some(new_tree), old_tree.as_ref(), 0)?;
-        for (parent, child) in &renderer.to_remove {
-            parent.remove_child(&child)?;
+        match html {
+            Html::Element(element) if node.node_name().eq_ignore_ascii_case(&element.name) => {
+                if let Children::Nodes(children) = &element.children {
+                    // The server renders the value of a text area as its content
+                    let has_value = element.attrs.iter().any(|attr| match attr {
+                        Attribute::Property(key, _) => key == "value",
+                        _ => false,
+                    });
+                    if !(element.name == "textarea" && has_value) {
+                        self.hydrate_children(&node, children)?;
+                    }
+                }
+                let current_node: &web_sys::Element = node.unchecked_ref();
+                for attr in &element.attrs {
+                    self.hydrate_attribute(current_node, attr)?;
+                }
+            }
+            Html::Text(text) if node.node_type() == web_sys::Node::TEXT_NODE => {
+                let current_node: &web_sys::Text = node.unchecked_ref();
+                let content = current_node.data();
+                if &content == text {
+                    return Ok(());
+                }
+                if content.starts_with(text.as_str()) {
+                    // The parser merges adjacent text into a single node, so it is split up
+                    // again. Offsets in the DOM are counted in UTF-16 code units.
+                    current_node.split_text(text.encode_utf16().count() as u32)?;
+                } else {
+                    log::warn!(
+                        "Hydration mismatch: expected text {:?}, found {:?}",
+                        text,
+                        content
+                    );
+                    current_node.set_data(text);
+                }
+            }
+            // Empty text produces no markup at all
+            Html::Text(text) if text.is_empty() => {
+                parent.insert_before(&self.create_node(html)?, Some(&node))?;
+            }
+            Html::Map(mapped) => mapped.0.hydrate(self, parent, Some(node))?,
+            Html::Lazy(lazy) => self.hydrate_node(parent, Some(node), &lazy.0.view())?,
+            _ => {
+                log::warn!(
+                    "Hydration mismatch: expected {}, found {} node",
+                    describe(html),
+                    node.node_name()
+                );
+                parent.replace_child(&self.create_node(html)?, &node)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn hydrate_attribute(
+        &self,
+        node: &web_sys::Element,
+        attribute: &Attribute<Msg>,
+    ) -> Result<(), JsValue> {
+        match attribute {
+            Attribute::Key(_) => {}
+            Attribute::Text(key, value) => {
+                if node.get_attribute(key).as_ref() != Some(value) {
+                    log::warn!(
+                        "Hydration mismatch: attribute {} should be {:?}",
+                        key,
+                        value
+                    );
+                    self.add_attribute(node, attribute)?;
+                }
+            }
+            Attribute::Namespaced(namespace, key, value) => {
+                if node
+                    .get_attribute_ns(Some(namespace), local_name(key))
+                    .as_ref()
+                    != Some(value)
+                {
+                    log::warn!(
+                        "Hydration mismatch: attribute {} should be {:?}",
+                        key,
+                        value
+                    );
+                    self.add_attribute(node, attribute)?;
+                }
+            }
+            Attribute::Bool(key, value) => {
+                if node.has_attribute(key) != *value {
+                    log::warn!("Hydration mismatch: attribute {} should be {}", key, value);
+                    self.add_attribute(node, attribute)?;
+                }
+            }
+            // Not every property is reflected in the markup, so these are set silently
+            Attribute::Property(key, value) => {
+                if get_property(node, key)? != to_js_value(value) {
+                    set_property(node, key, value)?;
+                }
+            }
+            Attribute::Style(_) | Attribute::Event(_) => self.add_attribute(node, attribute)?,
         }
         Ok(())
     }
@@ -71,104 +257,252 @@ where
                     // );
                 }
             }
-            (Some(old), Some(new)) => match (old, new) {
-                (Html::Element(old_tag), Html::Element(new_tag))
-                    if old_tag.name == new_tag.name && old_tag.key() == new_tag.key() =>
-                {
-                    let current_node: web_sys::Element = match parent.child_nodes().item(index) {
-                        Some(n) => n.dyn_into()?,
-                        None => {
-                            return Err(JsValue::from_str(&format!(
-                                "ERROR: Could not find node at index {}",
-                                index
-                            )));
-                        }
-                    };
-                    // We have a node (current_node) that has changed from old_tag to new_tag, though
-                    // the tag is still the same. This means we need to diff children and attributes
-
-                    // First we diff attributes
-                    // We start by removing the ones that are no longer active
-                    for old_attr in &old_tag.attrs {
-                        let new_attr = new_tag.attrs.iter().find(|e| e == &old_attr);
-                        if new_attr.is_none() {
-                            remove_attribute(&current_node, old_attr)?;
-                        } else if let Attribute::Event(old_listener) = old_attr {
-                            if let Some(Attribute::Event(new_listener)) = new_attr {
-                                if let Some(js_closure) =
-                                    old_listener.js_closure.0.borrow_mut().take()
-                                {
-                                    new_listener.js_closure.0.replace(Some(js_closure));
-                                }
-                            }
-                        }
-                    }
-                    // Then we add the ones that are added
-                    for attr in &new_tag.attrs {
-                        if !old_tag.attrs.contains(attr) {
-                            self.add_attribute(&current_node, attr)?;
-                        }
+            (Some(old), Some(new)) => {
+                let node = match parent.child_nodes().item(index) {
+                    Some(node) => node,
+                    None => {
+                        return Err(JsValue::from_str(&format!(
+                            "ERROR: Could not find node at index {}",
+                            index
+                        )));
                     }
+                };
+                self.patch(parent, &node, old, new)?;
+            }
+            (None, None) => {
+                // Should never happen, but if it happens we can just do nothing and it will be okay
+            }
+        }
 
-                    if let (Children::Nodes(old_children), Children::Nodes(new_children)) =
-                        (&old_tag.children, &new_tag.children)
-                    {
-                        for (child_index, pair) in old_children
-                            .iter()
-                            .zip_longest(new_children.iter())
-                            .enumerate()
-                        {
-                            let (old_child, new_child) = eiter_or_both_to_option_tuple(pair);
-
-                            self.update_element(
-                                &current_node,
-                                new_child,
-                                old_child,
-                                child_index as u32,
-                            )?;
+        Ok(())
+    }
+
+    /// Brings `node`, which was rendered from `old`, up to date with `new`. Returns the node
+    /// that ends up in the DOM, which is a new one when `node` had to be replaced.
+    pub(crate) fn patch(
+        &mut self,
+        parent: &web_sys::Node,
+        node: &web_sys::Node,
+        old: &Html<Msg>,
+        new: &Html<Msg>,
+    ) -> Result<web_sys::Node, JsValue> {
+        match (old, new) {
+            (Html::Lazy(old_lazy), Html::Lazy(new_lazy)) if new_lazy.0.same(&*old_lazy.0) => {
+                // Nothing can have changed, so the view is neither rendered nor diffed
+                new_lazy.0.reuse(&*old_lazy.0);
+                Ok(node.clone())
+            }
+            (Html::Lazy(old_lazy), new) => self.patch(parent, node, &old_lazy.0.view(), new),
+            (old, Html::Lazy(new_lazy)) => self.patch(parent, node, old, &new_lazy.0.view()),
+            (Html::Element(old_tag), Html::Element(new_tag))
+                if old_tag.name == new_tag.name
+                    && old_tag.namespace == new_tag.namespace
+                    && old_tag.key() == new_tag.key() =>
+            {
+                let current_node: &web_sys::Element = node.dyn_ref().ok_or_else(|| {
+                    JsValue::from_str(&format!("ERROR: Expected an element for {}", new_tag.name))
+                })?;
+                // We have a node (current_node) that has changed from old_tag to new_tag, though
+                // the tag is still the same. This means we need to diff children and attributes
+
+                // First we diff attributes
+                // We start by removing the ones that are no longer active
+                for old_attr in &old_tag.attrs {
+                    match old_attr {
+                        Attribute::Style(_) | Attribute::Event(_) => continue,
+                        // A property that is still there is overwritten below instead, as
+                        // clearing it first would move the caret of an input, for example
+                        Attribute::Property(key, _) if has_property(&new_tag.attrs, key) => {
+                            continue
                         }
+                        _ => {}
+                    }
+                    if !new_tag.attrs.contains(old_attr) {
+                        remove_attribute(current_node, old_attr)?;
                     }
                 }
-                (Html::Text(s1), Html::Text(s2)) => {
-                    if s1 != s2 {
-                        if let Some(child) = parent.child_nodes().item(index) {
-                            child.set_text_content(Some(&s2));
-                        } else {
-                            return Err(JsValue::from_str(&format!(
-                                "ERROR: Could not find node at index {}",
-                                index,
-                            )));
+                // Then we add the ones that are added
+                for attr in &new_tag.attrs {
+                    match attr {
+                        // The user may have changed a property like `value` since the last
+                        // render, so it is compared against the DOM instead of the old tree
+                        Attribute::Property(key, value)
+                            if get_property(current_node, key)? != to_js_value(value) =>
+                        {
+                            set_property(current_node, key, value)?
                         }
+                        Attribute::Style(_) | Attribute::Event(_) | Attribute::Property(..) => {}
+                        attr if !old_tag.attrs.contains(attr) => {
+                            self.add_attribute(current_node, attr)?
+                        }
+                        _ => {}
                     }
                 }
-                _ => {
-                    if let Some(child) = parent.child_nodes().item(index) {
-                        parent.replace_child(&self.create_node(new)?, &child)?;
-                    } else {
-                        return Err(JsValue::from_str(&format!(
-                            "ERROR: Could not find node at index {}",
-                            index,
-                        )));
-                    }
+
+                self.update_listeners(current_node, &old_tag.attrs, &new_tag.attrs)?;
+
+                // Styles are diffed one property at a time, so changing one property does not
+                // rewrite the whole `style` attribute
+                update_styles(current_node, &old_tag.attrs, &new_tag.attrs)?;
+
+                if let (Children::Nodes(old_children), Children::Nodes(new_children)) =
+                    (&old_tag.children, &new_tag.children)
+                {
+                    self.update_children(node, old_children, new_children)?;
                 }
-            },
-            (None, None) => {
-                // Should never happen, but if it happens we can just do nothing and it will be okay
+                Ok(node.clone())
+            }
+            (Html::Text(s1), Html::Text(s2)) => {
+                if s1 != s2 {
+                    node.set_text_content(Some(s2));
+                }
+                Ok(node.clone())
+            }
+            (Html::Map(old), Html::Map(new)) => new.0.patch(self, parent, node, &*old.0),
+            _ => {
+                let new_node = self.create_node(new)?;
+                parent.replace_child(&new_node, node)?;
+                Ok(new_node)
+            }
+        }
+    }
+
+    fn update_children(
+        &mut self,
+        parent: &web_sys::Node,
+        old_children: &[Html<Msg>],
+        new_children: &[Html<Msg>],
+    ) -> Result<(), JsValue> {
+        // Fragments and empty nodes have no DOM node of their own, so the children are
+        // flattened to line up with the child nodes
+        reuse_lazy(old_children, new_children);
+        let old_children = flatten(old_children);
+        let old_children = old_children
+            .iter()
+            .map(|child| &**child)
+            .collect::<Vec<_>>();
+        let new_children = flatten(new_children);
+        let new_children = new_children
+            .iter()
+            .map(|child| &**child)
+            .collect::<Vec<_>>();
+
+        if let (Some(old_keys), Some(new_keys)) = (keys(&old_children), keys(&new_children)) {
+            return self.update_keyed_children(
+                parent,
+                &old_children,
+                &new_children,
+                &old_keys,
+                &new_keys,
+            );
+        }
+
+        for (child_index, pair) in old_children
+            .iter()
+            .zip_longest(new_children.iter())
+            .enumerate()
+        {
+            let (old_child, new_child) = eiter_or_both_to_option_tuple(pair);
+            self.update_element(
+                parent,
+                new_child.cloned(),
+                old_child.cloned(),
+                child_index as u32,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Diffs children that all have a unique key. Children are matched by key instead of by
+    /// index, so existing DOM nodes are moved rather than patched into something else. Only
+    /// the children outside of the longest run that kept its relative order are moved.
+    fn update_keyed_children(
+        &mut self,
+        parent: &web_sys::Node,
+        old_children: &[&Html<Msg>],
+        new_children: &[&Html<Msg>],
+        old_keys: &[&str],
+        new_keys: &[&str],
+    ) -> Result<(), JsValue> {
+        let child_nodes = parent.child_nodes();
+        let mut old_nodes = Vec::with_capacity(old_children.len());
+        for index in 0..old_children.len() {
+            match child_nodes.item(index as u32) {
+                Some(node) => old_nodes.push(node),
+                None => {
+                    return Err(JsValue::from_str(&format!(
+                        "ERROR: Could not find node at index {}",
+                        index
+                    )));
+                }
+            }
+        }
+
+        let old_indexes = old_keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| (*key, index))
+            .collect::<HashMap<_, _>>();
+        let sources = new_keys
+            .iter()
+            .map(|key| old_indexes.get(key).cloned())
+            .collect::<Vec<_>>();
+
+        let mut reused = vec![false; old_children.len()];
+        for source in sources.iter().filter_map(|source| source.as_ref()) {
+            reused[*source] = true;
+        }
+        for (old_node, reused) in old_nodes.iter().zip(&reused) {
+            if !reused {
+                parent.remove_child(old_node)?;
             }
         }
 
+        let matched = sources
+            .iter()
+            .filter_map(|source| *source)
+            .collect::<Vec<_>>();
+        let mut stable = vec![false; old_children.len()];
+        for position in longest_increasing_subsequence(&matched) {
+            stable[matched[position]] = true;
+        }
+
+        // Walk backwards so that the node every child has to be inserted before is already
+        // in its final place
+        let mut next: Option<web_sys::Node> = None;
+        for (new_child, source) in new_children.iter().zip(&sources).rev() {
+            let node = match source {
+                Some(old_index) => {
+                    let old_node = &old_nodes[*old_index];
+                    let node = self.patch(parent, old_node, old_children[*old_index], new_child)?;
+                    if !stable[*old_index] {
+                        parent.insert_before(&node, next.as_ref())?;
+                    }
+                    node
+                }
+                None => {
+                    let node = self.create_node(new_child)?;
+                    parent.insert_before(&node, next.as_ref())?;
+                    node
+                }
+            };
+            next = Some(node);
+        }
         Ok(())
     }
 
-    fn create_node(&self, input: &Html<Msg>) -> Result<web_sys::Node, JsValue> {
+    pub(crate) fn create_node(&self, input: &Html<Msg>) -> Result<web_sys::Node, JsValue> {
         match input {
             Html::Element(Element {
                 name,
+                namespace,
                 attrs,
                 children,
-                ..
             }) => {
-                let el = self.program.browser.document.create_element(&name)?;
+                let el = match namespace {
+                    Some(namespace) => self.document.create_element_ns(Some(namespace), name)?,
+                    None => self.document.create_element(name)?,
+                };
 
                 for attr in attrs {
                     self.add_attribute(&el, attr)?;
@@ -178,7 +512,7 @@ where
 
                 if let Children::Nodes(children) = children {
                     for child in children {
-                        let child_node = self.create_node(&child)?;
+                        let child_node = self.create_node(child)?;
                         node.append_child(&child_node)?;
                     }
                 }
@@ -186,9 +520,21 @@ where
                 Ok(node)
             }
             Html::Text(text) => {
-                let node = self.program.browser.document.create_text_node(&text);
+                let node = self.document.create_text_node(text);
                 Ok(node.into())
             }
+            Html::Map(mapped) => mapped.0.create_node(self),
+            Html::Lazy(lazy) => self.create_node(&lazy.0.view()),
+            // Children are flattened before diffing, so these only end up here when they are
+            // appended as a whole
+            Html::Fragment(children) => {
+                let node: web_sys::Node = self.document.create_document_fragment().into();
+                for child in children {
+                    node.append_child(&self.create_node(child)?)?;
+                }
+                Ok(node)
+            }
+            Html::None => Ok(self.document.create_document_fragment().into()),
         }
     }
 
@@ -199,49 +545,125 @@ where
     ) -> Result<(), JsValue> {
         match attribute {
             Attribute::Key(_) => Ok(()),
-            Attribute::Text(key, value) => node.set_attribute(&key, &value),
-            Attribute::Bool(key) => node.set_attribute(&key, "true"),
-            Attribute::Event(EventListener {
-                type_,
+            Attribute::Text(key, value) => node.set_attribute(key, value),
+            Attribute::Namespaced(namespace, key, value) => {
+                node.set_attribute_ns(Some(namespace), key, value)
+            }
+            Attribute::Bool(key, true) => node.set_attribute(key, ""),
+            Attribute::Bool(key, false) => node.remove_attribute(key),
+            Attribute::Property(key, value) => set_property(node, key, value),
+            Attribute::Style(properties) => {
+                let style = get_style(node)?;
+                for (name, value) in properties {
+                    style.set_property(name, value)?;
+                }
+                Ok(())
+            }
+            Attribute::Event(listener) => self.add_listener(node, listener),
+        }
+    }
+
+    /// Diffs the event listeners of an element. Listeners for the same event are matched up
+    /// in order, and a listener that stays keeps its DOM listener and only gets the new
+    /// handler. Handlers like decoders are new closures on every render, so comparing them
+    /// would rebind every one of them.
+    fn update_listeners(
+        &self,
+        node: &web_sys::Element,
+        old_attrs: &[Attribute<Msg>],
+        new_attrs: &[Attribute<Msg>],
+    ) -> Result<(), JsValue> {
+        let mut new_listeners = listeners(new_attrs);
+        for old in listeners(old_attrs) {
+            match new_listeners.iter().position(|new| new.type_ == old.type_) {
+                Some(index) => {
+                    let new = new_listeners.remove(index);
+                    let js_closure = old.js_closure.0.borrow_mut().take();
+                    match js_closure {
+                        Some(js_closure) => {
+                            if let Some(listener) = js_closure.downcast_ref::<Listener<Msg>>() {
+                                listener.dispatcher.replace(self.dispatcher.clone());
+                                listener.handler.replace(handler(new));
+                            }
+                            new.js_closure.0.replace(Some(js_closure));
+                        }
+                        None => self.add_listener(node, new)?,
+                    }
+                }
+                None => remove_listener(node, old)?,
+            }
+        }
+        for new in new_listeners {
+            self.add_listener(node, new)?;
+        }
+        Ok(())
+    }
+
+    fn add_listener(
+        &self,
+        node: &web_sys::Element,
+        listener: &EventListener<Msg>,
+    ) -> Result<(), JsValue> {
+        let handler = Rc::new(RefCell::new(handler(listener)));
+        let closure_handler = handler.clone();
+        let dispatcher = Rc::new(RefCell::new(self.dispatcher.clone()));
+        let closure_dispatcher = dispatcher.clone();
+        let closure = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            // The handler is cloned so that the borrow ends before the update, which may
+            // render and replace it
+            let EventListener {
                 to_message,
                 stop_propagation,
                 prevent_default,
-                js_closure,
-            }) => {
-                let to_message = to_message.clone();
-                let program = self.program.clone();
-                let stop_propagation = *stop_propagation;
-                let prevent_default = *prevent_default;
-                let closure = Closure::wrap(Box::new(move |event: web_sys::Event| {
-                    if prevent_default {
-                        event.prevent_default();
-                    }
-                    if stop_propagation {
-                        event.stop_propagation();
-                    }
-                    let result = match &to_message {
-                        EventToMessage::StaticMsg(msg) => Program::dispatch(&program, msg),
-                    };
-                    if let Err(error) = result {
-                        log::error!("{:#?}", error);
-                    }
-                }) as Box<Fn(_)>);
+                sync,
+                ..
+            } = closure_handler.borrow().clone();
+            if prevent_default {
+                event.prevent_default();
+            }
+            if stop_propagation {
+                event.stop_propagation();
+            }
+            let custom = match to_message.handle(&event) {
+                Some(custom) => custom,
+                None => return,
+            };
+            if custom.prevent_default {
+                event.prevent_default();
+            }
+            if custom.stop_propagation {
+                event.stop_propagation();
+            }
+            let message = custom.message;
+            // The same goes for the dispatcher
+            let dispatcher = closure_dispatcher.borrow().clone();
+            if sync {
+                dispatcher.dispatch_sync(message);
+            } else {
+                dispatcher.dispatch(message);
+            }
+        }) as Box<dyn Fn(web_sys::Event)>);
 
-                let node_et: &web_sys::EventTarget = &node;
-                node_et
-                    .add_event_listener_with_callback(&type_, closure.as_ref().unchecked_ref())?;
+        let node_et: &web_sys::EventTarget = node;
+        node_et
+            .add_event_listener_with_callback(&listener.type_, closure.as_ref().unchecked_ref())?;
 
-                let ret = js_closure.0.replace(Some(closure));
-                if ret.is_some() {
-                    log::warn!("to_message did already have a closure???");
-                }
-                Ok(())
-            }
+        let ret = listener
+            .js_closure
+            .0
+            .replace(Some(Box::new(Listener::<Msg> {
+                closure,
+                dispatcher,
+                handler,
+            })));
+        if ret.is_some() {
+            log::warn!("to_message did already have a closure???");
         }
+        Ok(())
     }
 }
 
-fn remove_attribute<Msg>(
+fn remove_attribute<Msg: 'static>(
     node: &web_sys::Element,
     attribute: &Attribute<Msg>,
 ) -> Result<(), JsValue> {
@@ -251,23 +673,305 @@ fn remove_attribute<Msg>(
         Attribute::Text(key, _) => {
             node.remove_attribute(key)?;
         }
-        Attribute::Bool(key) => {
+        Attribute::Namespaced(namespace, key, _) => {
+            node.remove_attribute_ns(Some(namespace), local_name(key))?;
+        }
+        Attribute::Bool(key, _) => {
             node.remove_attribute(key)?;
         }
-        Attribute::Event(EventListener {
-            type_, js_closure, ..
-        }) => {
-            if let Some(closure) = js_closure.0.replace(None) {
-                let node_et: &web_sys::EventTarget = &node;
-                node_et.remove_event_listener_with_callback(
-                    &type_,
-                    closure.as_ref().unchecked_ref(),
-                )?;
-            } else {
-                log::warn!("Could not get a function to remove listener");
+        Attribute::Property(key, value) => {
+            // There is no removing a property, so it is reset to an empty value instead
+            let empty = match value {
+                Value::String(_) => Value::String(String::new()),
+                Value::Bool(_) => Value::Bool(false),
+                Value::Number(_) | Value::Null => Value::Null,
+            };
+            set_property(node, key, &empty)?;
+        }
+        Attribute::Style(properties) => {
+            let style = get_style(node)?;
+            for (name, _) in properties {
+                style.remove_property(name)?;
             }
         }
+        Attribute::Event(listener) => remove_listener(node, listener)?,
     }
 
     Ok(())
 }
+
+fn remove_listener<Msg: 'static>(
+    node: &web_sys::Element,
+    listener: &EventListener<Msg>,
+) -> Result<(), JsValue> {
+    let closure = listener.js_closure.0.replace(None);
+    if let Some(listener_closure) = closure
+        .as_ref()
+        .and_then(|closure| closure.downcast_ref::<Listener<Msg>>())
+    {
+        let node_et: &web_sys::EventTarget = node;
+        node_et.remove_event_listener_with_callback(
+            &listener.type_,
+            listener_closure.closure.as_ref().unchecked_ref(),
+        )?;
+    } else {
+        log::warn!("Could not get a function to remove listener");
+    }
+    Ok(())
+}
+
+/// The event listeners among `attrs`
+fn listeners<Msg>(attrs: &[Attribute<Msg>]) -> Vec<&EventListener<Msg>> {
+    attrs
+        .iter()
+        .filter_map(|attr| match attr {
+            Attribute::Event(listener) => Some(listener),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A copy of `listener` for its DOM listener to call, without the closure of the DOM
+/// listener itself
+fn handler<Msg: Clone>(listener: &EventListener<Msg>) -> EventListener<Msg> {
+    EventListener {
+        js_closure: Default::default(),
+        ..listener.clone()
+    }
+}
+
+/// Names a node of the view in hydration warnings
+fn describe<Msg>(html: &Html<Msg>) -> String {
+    match html {
+        Html::Element(element) => format!("<{}>", element.name),
+        Html::Text(text) => format!("text {:?}", text),
+        Html::Map(mapped) => format!("{:?}", mapped),
+        Html::Fragment(_) => "fragment".to_owned(),
+        Html::None => "nothing".to_owned(),
+        Html::Lazy(lazy) => describe(&lazy.0.view()),
+    }
+}
+
+/// The name of an attribute without its prefix, so `href` for `xlink:href`
+fn local_name(name: &str) -> &str {
+    match name.find(':') {
+        Some(index) => &name[index + 1..],
+        None => name,
+    }
+}
+
+fn to_js_value(value: &Value) -> JsValue {
+    match value {
+        Value::Null => JsValue::NULL,
+        Value::Bool(value) => JsValue::from_bool(*value),
+        Value::Number(value) => JsValue::from_f64(*value),
+        Value::String(value) => JsValue::from_str(value),
+    }
+}
+
+fn has_property<Msg>(attrs: &[Attribute<Msg>], key: &str) -> bool {
+    attrs
+        .iter()
+        .any(|attr| matches!(attr, Attribute::Property(other, _) if other == key))
+}
+
+fn get_property(node: &web_sys::Element, key: &str) -> Result<JsValue, JsValue> {
+    js_sys::Reflect::get(node, &JsValue::from_str(key))
+}
+
+fn set_property(node: &web_sys::Element, key: &str, value: &Value) -> Result<(), JsValue> {
+    js_sys::Reflect::set(node, &JsValue::from_str(key), &to_js_value(value))?;
+    Ok(())
+}
+
+fn get_style(node: &web_sys::Element) -> Result<web_sys::CssStyleDeclaration, JsValue> {
+    js_sys::Reflect::get(node, &JsValue::from_str("style"))?.dyn_into()
+}
+
+/// Collects the style properties of all `Style` attributes. Later ones win.
+fn styles<Msg>(attrs: &[Attribute<Msg>]) -> Vec<(&str, &str)> {
+    let mut styles: Vec<(&str, &str)> = vec![];
+    for attr in attrs {
+        if let Attribute::Style(properties) = attr {
+            for (name, value) in properties {
+                styles.retain(|(existing, _)| existing != name);
+                styles.push((name, value));
+            }
+        }
+    }
+    styles
+}
+
+fn update_styles<Msg>(
+    node: &web_sys::Element,
+    old_attrs: &[Attribute<Msg>],
+    new_attrs: &[Attribute<Msg>],
+) -> Result<(), JsValue> {
+    let old_styles = styles(old_attrs);
+    let new_styles = styles(new_attrs);
+    if old_styles == new_styles {
+        return Ok(());
+    }
+    let style = get_style(node)?;
+    for (name, _) in &old_styles {
+        if !new_styles.iter().any(|(new_name, _)| new_name == name) {
+            style.remove_property(name)?;
+        }
+    }
+    for (name, value) in &new_styles {
+        if !old_styles.contains(&(name, value)) {
+            style.set_property(name, value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces fragments by their children and leaves out empty nodes
+fn flatten<Msg: Clone>(children: &[Html<Msg>]) -> Vec<Cow<'_, Html<Msg>>> {
+    let mut flattened = Vec::with_capacity(children.len());
+    for child in children {
+        match child {
+            Html::Fragment(children) => flattened.extend(flatten(children)),
+            Html::None => {}
+            // Only lazy and mapped views that render fragments are copied
+            child => match child.expand() {
+                Some(nodes) => flattened.extend(nodes.into_iter().map(Cow::Owned)),
+                None => flattened.push(Cow::Borrowed(child)),
+            },
+        }
+    }
+    flattened
+}
+
+/// Lets the lazy views among `new_children` take over the rendered views of the same ones
+/// among `old_children`, at the same index or with the same key, before they are flattened
+fn reuse_lazy<Msg: Clone>(old_children: &[Html<Msg>], new_children: &[Html<Msg>]) {
+    let old_keyed = old_children
+        .iter()
+        .filter_map(|child| child.key().map(|key| (key, child)))
+        .collect::<HashMap<_, _>>();
+    for (index, new_child) in new_children.iter().enumerate() {
+        let old_child = match new_child.key() {
+            Some(key) => old_keyed.get(key).cloned(),
+            None => old_children.get(index),
+        };
+        if let Some(old_child) = old_child {
+            new_child.reuse_lazy(old_child);
+        }
+    }
+}
+
+/// Returns the keys of `children` when every child is an element with a key, and no key is
+/// used twice. Otherwise children are diffed by index.
+fn keys<'a, Msg>(children: &[&'a Html<Msg>]) -> Option<Vec<&'a str>> {
+    let mut seen = HashSet::new();
+    let mut keys = Vec::with_capacity(children.len());
+    for child in children {
+        let key = child.key()?;
+        if !seen.insert(key) {
+            return None;
+        }
+        keys.push(key);
+    }
+    Some(keys)
+}
+
+/// Returns the positions in `sequence` that make up one of its longest increasing
+/// subsequences, in ascending order.
+fn longest_increasing_subsequence(sequence: &[usize]) -> Vec<usize> {
+    let mut predecessors = vec![0; sequence.len()];
+    // tails[n] is the position of the smallest value that ends an increasing subsequence
+    // of length n + 1
+    let mut tails: Vec<usize> = vec![];
+    for (position, value) in sequence.iter().enumerate() {
+        let length = match tails.binary_search_by(|tail| sequence[*tail].cmp(value)) {
+            Ok(length) | Err(length) => length,
+        };
+        if length > 0 {
+            predecessors[position] = tails[length - 1];
+        }
+        if length == tails.len() {
+            tails.push(position);
+        } else {
+            tails[length] = position;
+        }
+    }
+
+    let mut result = vec![0; tails.len()];
+    if let Some(last) = tails.last() {
+        let mut current = *last;
+        for slot in result.iter_mut().rev() {
+            *slot = current;
+            current = predecessors[current];
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::{li, text};
+
+    fn item(key: Option<&str>) -> Html<()> {
+        let attrs = key.map(|key| Attribute::Key(key.to_owned()));
+        li(attrs.into_iter().collect::<Vec<_>>(), [text("item")])
+    }
+
+    /// Every increasing subsequence of `sequence` is checked, so this is only for short ones
+    fn longest_by_brute_force(sequence: &[usize]) -> usize {
+        (0..1u32 << sequence.len())
+            .map(|subset| {
+                (0..sequence.len())
+                    .filter(|position| subset & (1 << position) != 0)
+                    .map(|position| sequence[position])
+                    .collect::<Vec<_>>()
+            })
+            .filter(|values| values.windows(2).all(|pair| pair[0] < pair[1]))
+            .map(|values| values.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn finds_a_longest_increasing_subsequence() {
+        assert_eq!(longest_increasing_subsequence(&[]), Vec::<usize>::new());
+        assert_eq!(longest_increasing_subsequence(&[2, 0, 1, 3]), vec![1, 2, 3]);
+        assert_eq!(longest_increasing_subsequence(&[3, 2, 1]).len(), 1);
+        assert_eq!(longest_increasing_subsequence(&[1, 1, 1]).len(), 1);
+    }
+
+    #[test]
+    fn finds_subsequences_as_long_as_brute_force_does() {
+        let mut sequence = vec![0; 5];
+        for mut n in 0..5usize.pow(5) {
+            for value in sequence.iter_mut() {
+                *value = n % 5;
+                n /= 5;
+            }
+            let positions = longest_increasing_subsequence(&sequence);
+            assert_eq!(positions.len(), longest_by_brute_force(&sequence));
+            assert!(positions
+                .windows(2)
+                .all(|pair| pair[0] < pair[1] && sequence[pair[0]] < sequence[pair[1]]));
+        }
+    }
+
+    #[test]
+    fn diffs_by_key_only_when_every_child_has_a_distinct_key() {
+        let keyed = [item(Some("a")), item(Some("b"))];
+        assert_eq!(
+            keys(&keyed.iter().collect::<Vec<_>>()),
+            Some(vec!["a", "b"])
+        );
+
+        let duplicated = [item(Some("a")), item(Some("a"))];
+        assert_eq!(keys(&duplicated.iter().collect::<Vec<_>>()), None);
+
+        let mixed = [item(Some("a")), item(None)];
+        assert_eq!(keys(&mixed.iter().collect::<Vec<_>>()), None);
+
+        let with_text = [item(Some("a")), text("b")];
+        assert_eq!(keys(&with_text.iter().collect::<Vec<_>>()), None);
+    }
+}
@@ -0,0 +1,45 @@
+use std::cell::Cell;
+use wasm_bindgen::prelude::{Closure, JsValue};
+use wasm_bindgen::JsCast;
+
+/// Coalesces render requests so that a program renders at most once per animation frame.
+pub struct RenderScheduler {
+    dirty: Cell<bool>,
+    frame_requested: Cell<bool>,
+}
+
+impl RenderScheduler {
+    pub fn new() -> Self {
+        RenderScheduler {
+            dirty: Cell::new(false),
+            frame_requested: Cell::new(false),
+        }
+    }
+
+    /// Marks the view as out of date and makes sure `render` gets called on the next
+    /// animation frame. Several calls before that frame result in a single render.
+    pub fn schedule<F>(&self, window: &web_sys::Window, render: F) -> Result<(), JsValue>
+    where
+        F: FnOnce() + 'static,
+    {
+        self.dirty.set(true);
+        if self.frame_requested.replace(true) {
+            return Ok(());
+        }
+        let callback = Closure::once_into_js(render);
+        window.request_animation_frame(callback.unchecked_ref())?;
+        Ok(())
+    }
+
+    /// Called from the animation frame. Returns whether the view still needs rendering, which
+    /// is not the case when a synchronous render happened in the meantime.
+    pub fn frame(&self) -> bool {
+        self.frame_requested.set(false);
+        self.dirty.replace(false)
+    }
+
+    /// Called after rendering synchronously, so that a pending frame has nothing left to do.
+    pub fn rendered(&self) {
+        self.dirty.set(false);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::platform::Subscription;
+use crate::sse::{Backend, Event, ReadyState, Source, Status};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::JsValue;
+
+/// A backend for tests, which stands in for the server of every event source. Sources only
+/// open, receive events, drop or close when the test says so.
+///
+/// ```
+/// use oak::platform::Subs;
+/// use oak::sse::{self, MockBackend};
+/// use oak::{Dispatcher, Sub};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let mock = MockBackend::new();
+/// sse::set_backend(mock.clone());
+///
+/// let messages = Rc::new(RefCell::new(vec![]));
+/// let received = messages.clone();
+/// let dispatcher = Dispatcher::new(move |message| received.borrow_mut().push(message));
+/// let mut subs = Subs::new();
+/// sse::listen("/prices", "price", |price| price).collect(&mut subs);
+/// let subscriptions = subs.start(&dispatcher).unwrap();
+///
+/// mock.open("/prices");
+/// mock.send("/prices", "price", "42");
+/// assert_eq!(*messages.borrow(), vec!["42".to_owned()]);
+///
+/// drop(subscriptions);
+/// assert!(!mock.is_connected("/prices"));
+/// ```
+#[derive(Clone, Default)]
+pub struct MockBackend(Rc<RefCell<State>>);
+
+#[derive(Default)]
+struct State {
+    sources: Vec<MockSource>,
+    next_listener: usize,
+}
+
+struct MockSource {
+    url: String,
+    ready_state: ReadyState,
+    last_event_id: String,
+    listeners: Vec<(usize, String, EventListener)>,
+    status_listeners: Vec<(usize, StatusListener)>,
+}
+
+type EventListener = Rc<dyn Fn(Event)>;
+type StatusListener = Rc<dyn Fn(Status)>;
+
+impl MockBackend {
+    pub fn new() -> Self {
+        MockBackend::default()
+    }
+
+    /// How many sources to `url` were opened so far
+    pub fn connections(&self, url: &str) -> usize {
+        self.0
+            .borrow()
+            .sources
+            .iter()
+            .filter(|source| source.url == url)
+            .count()
+    }
+
+    /// Whether there is a source to `url` that is not closed
+    pub fn is_connected(&self, url: &str) -> bool {
+        self.0
+            .borrow()
+            .sources
+            .iter()
+            .any(|source| source.url == url && source.ready_state != ReadyState::Closed)
+    }
+
+    /// Accepts the connection of the source to `url`
+    pub fn open(&self, url: &str) {
+        self.set_ready_state(url, ReadyState::Open, Status::Open)
+    }
+
+    /// Sends the event named `event` with `data` from the server to `url`
+    pub fn send(&self, url: &str, event: &str, data: &str) {
+        self.emit(url, event, data, None)
+    }
+
+    /// Like `send`, but with an `id:`, which the event and those after it report
+    pub fn send_with_id(&self, url: &str, event: &str, id: &str, data: &str) {
+        self.emit(url, event, data, Some(id))
+    }
+
+    /// Drops the connection of the source to `url`, which then reconnects
+    pub fn drop_connection(&self, url: &str) {
+        self.set_ready_state(url, ReadyState::Connecting, Status::Reconnecting)
+    }
+
+    /// Gives up on the source to `url`, like when its server answers with an error
+    pub fn close(&self, url: &str) {
+        self.set_ready_state(url, ReadyState::Closed, Status::Closed)
+    }
+
+    fn set_ready_state(&self, url: &str, ready_state: ReadyState, status: Status) {
+        let listeners = {
+            let mut state = self.0.borrow_mut();
+            let source = match find(&mut state, url) {
+                Some(source) => source,
+                None => return,
+            };
+            source.ready_state = ready_state;
+            source
+                .status_listeners
+                .iter()
+                .map(|(_, listener)| listener.clone())
+                .collect::<Vec<_>>()
+        };
+        // The state is not borrowed here, as the message may start more subscriptions
+        for listener in listeners {
+            listener(status);
+        }
+    }
+
+    fn emit(&self, url: &str, name: &str, data: &str, id: Option<&str>) {
+        let (listeners, last_event_id) = {
+            let mut state = self.0.borrow_mut();
+            let source = match find(&mut state, url) {
+                Some(source) => source,
+                None => return,
+            };
+            if let Some(id) = id {
+                source.last_event_id = id.to_owned();
+            }
+            let listeners = source
+                .listeners
+                .iter()
+                .filter(|(_, event, _)| event == name)
+                .map(|(_, _, listener)| listener.clone())
+                .collect::<Vec<_>>();
+            (listeners, source.last_event_id.clone())
+        };
+        for listener in listeners {
+            listener(Event {
+                data: data.to_owned(),
+                last_event_id: last_event_id.clone(),
+            });
+        }
+    }
+}
+
+/// The source to `url` that is not closed, if there is one
+fn find<'a>(state: &'a mut State, url: &str) -> Option<&'a mut MockSource> {
+    state
+        .sources
+        .iter_mut()
+        .rev()
+        .find(|source| source.url == url && source.ready_state != ReadyState::Closed)
+}
+
+impl Backend for MockBackend {
+    fn connect(&self, url: &str, _: bool) -> Result<Rc<dyn Source>, JsValue> {
+        let mut state = self.0.borrow_mut();
+        state.sources.push(MockSource {
+            url: url.to_owned(),
+            ready_state: ReadyState::Connecting,
+            last_event_id: String::new(),
+            listeners: vec![],
+            status_listeners: vec![],
+        });
+        Ok(Rc::new(SourceHandle {
+            backend: self.clone(),
+            index: state.sources.len() - 1,
+        }))
+    }
+}
+
+struct SourceHandle {
+    backend: MockBackend,
+    index: usize,
+}
+
+impl SourceHandle {
+    fn next_listener(&self) -> usize {
+        let mut state = self.backend.0.borrow_mut();
+        state.next_listener += 1;
+        state.next_listener
+    }
+}
+
+impl Source for SourceHandle {
+    fn ready_state(&self) -> ReadyState {
+        self.backend.0.borrow().sources[self.index].ready_state
+    }
+
+    fn on_event(&self, name: &str, listener: Box<dyn Fn(Event)>) -> Result<Subscription, JsValue> {
+        let id = self.next_listener();
+        self.backend.0.borrow_mut().sources[self.index]
+            .listeners
+            .push((id, name.to_owned(), listener.into()));
+        let backend = self.backend.clone();
+        let index = self.index;
+        Ok(Subscription::new(move || {
+            backend.0.borrow_mut().sources[index]
+                .listeners
+                .retain(|(other_id, _, _)| *other_id != id)
+        }))
+    }
+
+    fn on_status(&self, listener: Box<dyn Fn(Status)>) -> Result<Subscription, JsValue> {
+        let id = self.next_listener();
+        self.backend.0.borrow_mut().sources[self.index]
+            .status_listeners
+            .push((id, listener.into()));
+        let backend = self.backend.clone();
+        let index = self.index;
+        Ok(Subscription::new(move || {
+            backend.0.borrow_mut().sources[index]
+                .status_listeners
+                .retain(|(other_id, _)| *other_id != id)
+        }))
+    }
+
+    fn close(&self) {
+        self.backend.0.borrow_mut().sources[self.index].ready_state = ReadyState::Closed;
+    }
+}
@@ -0,0 +1,344 @@
+//! Server-sent events. The `listen` subscription keeps an `EventSource` open for as long as
+//! the program is subscribed. Subscriptions to the same URL share one connection, which is
+//! closed when the last of them ends.
+//!
+//! The browser reconnects on its own when the connection drops, waiting as long as the
+//! server asked for with `retry:`, and resumes the stream by sending the id of the last
+//! event it got as `Last-Event-ID`. `on_status` reports when that happens.
+//!
+//! ```no_run
+//! use oak::sse::{self, Status};
+//! use oak::Sub;
+//!
+//! enum Msg {
+//!     Price(String),
+//!     Connection(Status),
+//! }
+//!
+//! fn subscriptions() -> impl Sub<Msg> {
+//!     sse::listen("/prices", "price", Msg::Price).on_status(Msg::Connection)
+//! }
+//! ```
+
+mod mock;
+mod web;
+
+pub use self::mock::MockBackend;
+pub use self::web::EventSourceBackend;
+use crate::platform::{Sub, Subs, Subscription};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::JsValue;
+
+/// An event with its id, for `listen_events`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub data: String,
+    /// The id of the last event that had one, which the browser sends when it reconnects
+    pub last_event_id: String,
+}
+
+/// The state of the connection of a subscription
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Open,
+    /// The connection dropped, and the browser is about to reconnect
+    Reconnecting,
+    /// The browser gave up, for example because the server answered with an error. The
+    /// subscription has to end and start again to reconnect.
+    Closed,
+}
+
+/// A subscription to the events named `event` sent by `url`, which hands their data to
+/// `to_msg`. Events without an `event:` field are named `"message"`.
+pub fn listen<Msg>(url: &str, event: &str, to_msg: fn(String) -> Msg) -> Listen<Msg> {
+    Listen::new(url, event, ToMsg::Data(to_msg))
+}
+
+/// Like `listen`, but hands over the id of the last event too
+pub fn listen_events<Msg>(url: &str, event: &str, to_msg: fn(Event) -> Msg) -> Listen<Msg> {
+    Listen::new(url, event, ToMsg::Event(to_msg))
+}
+
+pub struct Listen<Msg> {
+    url: String,
+    event: String,
+    to_msg: ToMsg<Msg>,
+    on_status: Option<fn(Status) -> Msg>,
+    with_credentials: bool,
+}
+
+enum ToMsg<Msg> {
+    Data(fn(String) -> Msg),
+    Event(fn(Event) -> Msg),
+}
+
+impl<Msg> Clone for ToMsg<Msg> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Msg> Copy for ToMsg<Msg> {}
+
+impl<Msg> ToMsg<Msg> {
+    /// The address of the function, which tells subscriptions apart
+    fn address(self) -> *const () {
+        match self {
+            ToMsg::Data(to_msg) => to_msg as *const (),
+            ToMsg::Event(to_msg) => to_msg as *const (),
+        }
+    }
+}
+
+impl<Msg> Listen<Msg> {
+    fn new(url: &str, event: &str, to_msg: ToMsg<Msg>) -> Self {
+        Listen {
+            url: url.to_owned(),
+            event: event.to_owned(),
+            to_msg,
+            on_status: None,
+            with_credentials: false,
+        }
+    }
+
+    /// Reports the state of the connection to `to_msg` as it changes
+    pub fn on_status(mut self, to_msg: fn(Status) -> Msg) -> Self {
+        self.on_status = Some(to_msg);
+        self
+    }
+
+    /// Whether cookies are sent to other origins too
+    pub fn with_credentials(mut self, with_credentials: bool) -> Self {
+        self.with_credentials = with_credentials;
+        self
+    }
+}
+
+impl<Msg: 'static> Sub<Msg> for Listen<Msg> {
+    fn collect(&self, subs: &mut Subs<Msg>) {
+        let url = self.url.clone();
+        let event = self.event.clone();
+        let to_msg = self.to_msg;
+        let on_status = self.on_status;
+        let with_credentials = self.with_credentials;
+        let key = format!(
+            "sse::listen/{}/{}/{}/{:p}/{:?}",
+            url,
+            event,
+            with_credentials,
+            to_msg.address(),
+            on_status.map(|on_status| format!("{:p}", on_status))
+        );
+        subs.add(key, move |dispatcher| {
+            let source = acquire(&url, with_credentials)?;
+            // Releases the source even when listening to it fails below
+            let released = source.clone();
+            let mut subscriptions = vec![Subscription::new(move || {
+                release(&url, with_credentials, &released)
+            })];
+
+            let message_dispatcher = dispatcher.clone();
+            subscriptions.push(source.on_event(
+                &event,
+                Box::new(move |event| {
+                    message_dispatcher.dispatch(match to_msg {
+                        ToMsg::Data(to_msg) => to_msg(event.data),
+                        ToMsg::Event(to_msg) => to_msg(event),
+                    })
+                }),
+            )?);
+
+            if let Some(on_status) = on_status {
+                let status_dispatcher = dispatcher.clone();
+                subscriptions.push(source.on_status(Box::new(move |status| {
+                    status_dispatcher.dispatch(on_status(status))
+                }))?);
+                // A shared connection may be open already, and won't say so again
+                if source.ready_state() == ReadyState::Open {
+                    dispatcher.dispatch(on_status(Status::Open));
+                }
+            }
+            Ok(Subscription::new(move || drop(subscriptions)))
+        });
+    }
+}
+
+/// Whether a source is connected, like the `readyState` of an `EventSource`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadyState {
+    Connecting,
+    Open,
+    Closed,
+}
+
+/// Opens the connections of the subscriptions in this module. `EventSourceBackend` is the
+/// default, and tests without a browser use a `MockBackend` instead.
+pub trait Backend {
+    /// Opens a source of the events sent by `url`
+    fn connect(&self, url: &str, with_credentials: bool) -> Result<Rc<dyn Source>, JsValue>;
+}
+
+/// A source opened by a backend, which reconnects on its own until it is `Closed`
+pub trait Source {
+    fn ready_state(&self) -> ReadyState;
+
+    /// Calls `listener` with the events named `name` until the returned subscription ends
+    fn on_event(&self, name: &str, listener: Box<dyn Fn(Event)>) -> Result<Subscription, JsValue>;
+
+    /// Calls `listener` as the connection opens, drops or is given up on, until the returned
+    /// subscription ends
+    fn on_status(&self, listener: Box<dyn Fn(Status)>) -> Result<Subscription, JsValue>;
+
+    /// Closes the source without reporting a status
+    fn close(&self);
+}
+
+thread_local! {
+    static BACKEND: RefCell<Rc<dyn Backend>> = RefCell::new(Rc::new(EventSourceBackend));
+    /// The open sources, with how many subscriptions use them
+    static SOURCES: RefCell<HashMap<(String, bool), (SharedSource, usize)>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Opens the connections of every subscription that starts after this with `backend`
+pub fn set_backend<B: Backend + 'static>(backend: B) {
+    BACKEND.with(|current| *current.borrow_mut() = Rc::new(backend));
+}
+
+fn backend() -> Rc<dyn Backend> {
+    BACKEND.with(|backend| backend.borrow().clone())
+}
+
+type SharedSource = Rc<dyn Source>;
+
+fn acquire(url: &str, with_credentials: bool) -> Result<Rc<dyn Source>, JsValue> {
+    let key = (url.to_owned(), with_credentials);
+    let shared = SOURCES.with(|sources| match sources.borrow_mut().get_mut(&key) {
+        // A source that was given up on is replaced, so that new subscriptions connect again
+        Some((source, users)) if source.ready_state() != ReadyState::Closed => {
+            *users += 1;
+            Some(source.clone())
+        }
+        _ => None,
+    });
+    if let Some(source) = shared {
+        return Ok(source);
+    }
+    let source = backend().connect(url, with_credentials)?;
+    SOURCES.with(|sources| sources.borrow_mut().insert(key, (source.clone(), 1)));
+    Ok(source)
+}
+
+fn release(url: &str, with_credentials: bool, source: &Rc<dyn Source>) {
+    let unused = SOURCES.with(|sources| {
+        let mut sources = sources.borrow_mut();
+        let key = (url.to_owned(), with_credentials);
+        match sources.get_mut(&key) {
+            Some((shared, users)) if Rc::ptr_eq(shared, source) => {
+                *users -= 1;
+                if *users == 0 {
+                    sources.remove(&key);
+                    true
+                } else {
+                    false
+                }
+            }
+            // The source was replaced after it closed, and nobody else uses it
+            _ => true,
+        }
+    });
+    if unused {
+        source.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Dispatcher;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Msg {
+        Data(String),
+        Event(Event),
+        Status(Status),
+    }
+
+    type Messages = Rc<RefCell<Vec<Msg>>>;
+
+    fn subscribe(sub: Listen<Msg>) -> (Vec<Subscription>, Messages) {
+        let messages = Messages::default();
+        let received = messages.clone();
+        let dispatcher = Dispatcher::new(move |message| received.borrow_mut().push(message));
+        let mut subs = Subs::new();
+        sub.collect(&mut subs);
+        (subs.start(&dispatcher).unwrap(), messages)
+    }
+
+    #[test]
+    fn hands_events_and_statuses_to_subscriptions() {
+        let mock = MockBackend::new();
+        set_backend(mock.clone());
+        let (_data, data) = subscribe(listen("/feed", "price", Msg::Data).on_status(Msg::Status));
+        let (_events, events) = subscribe(listen_events("/feed", "message", Msg::Event));
+
+        mock.open("/feed");
+        mock.send("/feed", "price", "42");
+        mock.send_with_id("/feed", "message", "7", "hi");
+        mock.drop_connection("/feed");
+        assert_eq!(
+            *data.borrow(),
+            vec![
+                Msg::Status(Status::Open),
+                Msg::Data("42".to_owned()),
+                Msg::Status(Status::Reconnecting),
+            ]
+        );
+        let event = Event {
+            data: "hi".to_owned(),
+            last_event_id: "7".to_owned(),
+        };
+        assert_eq!(*events.borrow(), vec![Msg::Event(event)]);
+    }
+
+    #[test]
+    fn shares_a_source_until_the_last_subscription_ends() {
+        let mock = MockBackend::new();
+        set_backend(mock.clone());
+        let (first, _) = subscribe(listen("/shared", "a", Msg::Data));
+        mock.open("/shared");
+        let (second, messages) =
+            subscribe(listen("/shared", "b", Msg::Data).on_status(Msg::Status));
+        assert_eq!(mock.connections("/shared"), 1);
+        // The source was open before the second subscription started
+        assert_eq!(*messages.borrow(), vec![Msg::Status(Status::Open)]);
+
+        drop(first);
+        assert!(mock.is_connected("/shared"));
+        drop(second);
+        assert!(!mock.is_connected("/shared"));
+    }
+
+    #[test]
+    fn reconnects_new_subscriptions_to_a_closed_source() {
+        let mock = MockBackend::new();
+        set_backend(mock.clone());
+        let (closed, messages) =
+            subscribe(listen("/closed", "a", Msg::Data).on_status(Msg::Status));
+        mock.close("/closed");
+        assert_eq!(*messages.borrow(), vec![Msg::Status(Status::Closed)]);
+
+        let (reconnected, messages) = subscribe(listen("/closed", "b", Msg::Data));
+        assert_eq!(mock.connections("/closed"), 2);
+        mock.send("/closed", "b", "again");
+        assert_eq!(*messages.borrow(), vec![Msg::Data("again".to_owned())]);
+
+        // Ending the subscription to the closed source leaves the new one alone
+        drop(closed);
+        assert!(mock.is_connected("/closed"));
+        drop(reconnected);
+        assert!(!mock.is_connected("/closed"));
+    }
+}
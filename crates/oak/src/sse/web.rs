@@ -0,0 +1,87 @@
+use crate::platform::Subscription;
+use crate::sse::{Backend, Event, ReadyState, Source, Status};
+use std::rc::Rc;
+use wasm_bindgen::prelude::{Closure, JsValue};
+use wasm_bindgen::JsCast;
+
+/// The backend of the browser, which opens an `EventSource`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EventSourceBackend;
+
+impl Backend for EventSourceBackend {
+    fn connect(&self, url: &str, with_credentials: bool) -> Result<Rc<dyn Source>, JsValue> {
+        let init = web_sys::EventSourceInit::new();
+        init.set_with_credentials(with_credentials);
+        let source = web_sys::EventSource::new_with_event_source_init_dict(url, &init)?;
+        Ok(Rc::new(EventSource(source)))
+    }
+}
+
+struct EventSource(web_sys::EventSource);
+
+impl EventSource {
+    /// Calls `handler` with the events named `name` until the subscription is dropped
+    fn listen(
+        &self,
+        name: &str,
+        handler: Box<dyn FnMut(web_sys::Event)>,
+    ) -> Result<Subscription, JsValue> {
+        let handler = Closure::wrap(handler);
+        self.0
+            .add_event_listener_with_callback(name, handler.as_ref().unchecked_ref())?;
+        let source = self.0.clone();
+        let name = name.to_owned();
+        Ok(Subscription::new(move || {
+            let _ =
+                source.remove_event_listener_with_callback(&name, handler.as_ref().unchecked_ref());
+        }))
+    }
+}
+
+impl Source for EventSource {
+    fn ready_state(&self) -> ReadyState {
+        match self.0.ready_state() {
+            web_sys::EventSource::OPEN => ReadyState::Open,
+            web_sys::EventSource::CLOSED => ReadyState::Closed,
+            _ => ReadyState::Connecting,
+        }
+    }
+
+    fn on_event(&self, name: &str, listener: Box<dyn Fn(Event)>) -> Result<Subscription, JsValue> {
+        self.listen(
+            name,
+            Box::new(move |event: web_sys::Event| {
+                let event = event.unchecked_ref::<web_sys::MessageEvent>();
+                listener(Event {
+                    data: event.data().as_string().unwrap_or_default(),
+                    last_event_id: event.last_event_id(),
+                })
+            }),
+        )
+    }
+
+    fn on_status(&self, listener: Box<dyn Fn(Status)>) -> Result<Subscription, JsValue> {
+        let listener: Rc<dyn Fn(Status)> = listener.into();
+        let open_listener = listener.clone();
+        let open = self.listen(
+            "open",
+            Box::new(move |_: web_sys::Event| open_listener(Status::Open)),
+        )?;
+        let source = self.0.clone();
+        let error = self.listen(
+            "error",
+            Box::new(move |_: web_sys::Event| {
+                listener(if source.ready_state() == web_sys::EventSource::CLOSED {
+                    Status::Closed
+                } else {
+                    Status::Reconnecting
+                })
+            }),
+        )?;
+        Ok(Subscription::new(move || drop((open, error))))
+    }
+
+    fn close(&self) {
+        self.0.close()
+    }
+}
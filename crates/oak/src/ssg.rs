@@ -0,0 +1,327 @@
+//! Generates a static site by rendering a view for every route to an `index.html`.
+//!
+//! ```
+//! use oak::html::{h1, Html};
+//! use oak::ssg::Site;
+//!
+//! fn page(route: &&str) -> ((), Html<()>) {
+//!     ((), h1([], [format!("Welcome to {}", route)]))
+//! }
+//!
+//! let out_dir = std::env::temp_dir().join("oak-ssg-example");
+//! let written = Site::new(vec!["/", "/about"], page)
+//!     .out_dir(&out_dir)
+//!     .head("<title>Oak</title>")
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(written, vec![out_dir.join("index.html"), out_dir.join("about/index.html")]);
+//! ```
+
+use crate::html::Html;
+use crate::ssr;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// The shell used when none is configured. The page is rendered into `#app`, which is also
+/// where a hydrating program should be mounted.
+pub const DEFAULT_SHELL: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    {{head}}
+  </head>
+  <body>
+    <div id="app">{{body}}</div>
+    {{scripts}}
+  </body>
+</html>
+"#;
+
+/// A route that can be generated. Its path decides where its `index.html` is written.
+pub trait Route {
+    fn path(&self) -> String;
+}
+
+impl Route for &str {
+    fn path(&self) -> String {
+        (*self).to_owned()
+    }
+}
+
+impl Route for String {
+    fn path(&self) -> String {
+        self.clone()
+    }
+}
+
+type Page<R, Model, Msg> = dyn Fn(&R) -> (Model, Html<Msg>);
+
+type Serialize<Model> = dyn Fn(&Model) -> String;
+
+pub struct Site<R, Model, Msg> {
+    routes: Vec<R>,
+    page: Box<Page<R, Model, Msg>>,
+    out_dir: PathBuf,
+    shell: String,
+    head: Vec<String>,
+    /// The script that hydrates the pages, if any
+    hydration: Option<String>,
+    state: Option<Box<Serialize<Model>>>,
+}
+
+impl<R, Model, Msg> Site<R, Model, Msg>
+where
+    R: Route,
+{
+    /// A site with a page for each of `routes`, rendered by `page`
+    pub fn new<F>(routes: Vec<R>, page: F) -> Self
+    where
+        F: Fn(&R) -> (Model, Html<Msg>) + 'static,
+    {
+        Site {
+            routes,
+            page: Box::new(page),
+            out_dir: PathBuf::from("dist"),
+            shell: DEFAULT_SHELL.to_owned(),
+            head: vec![],
+            hydration: None,
+            state: None,
+        }
+    }
+
+    /// Where pages are written. Defaults to `dist`.
+    pub fn out_dir<P: AsRef<Path>>(mut self, out_dir: P) -> Self {
+        self.out_dir = out_dir.as_ref().to_owned();
+        self
+    }
+
+    /// The HTML every page is rendered into. `{{body}}` is replaced by the rendered view,
+    /// `{{head}}` by the head tags and `{{scripts}}` by the hydration bootstrap, if any.
+    pub fn shell(mut self, shell: &str) -> Self {
+        self.shell = shell.to_owned();
+        self
+    }
+
+    /// Adds a tag, like `<title>` or `<link>`, to the head of every page
+    pub fn head(mut self, tag: &str) -> Self {
+        self.head.push(tag.to_owned());
+        self
+    }
+
+    /// Loads `script` as a module on every page, so the program can hydrate the markup
+    pub fn hydrate(mut self, script: &str) -> Self {
+        self.hydration = Some(script.to_owned());
+        self
+    }
+
+    /// Embeds the model of each page, serialized by `serialize`, in a
+    /// `<script type="application/json" id="oak-state">` tag, so the program can start from the
+    /// same model the page was rendered with by reading it with `read_state`. Only used
+    /// together with `hydrate`, which may be called before or after this.
+    pub fn state<F>(mut self, serialize: F) -> Self
+    where
+        F: Fn(&Model) -> String + 'static,
+    {
+        self.state = Some(Box::new(serialize));
+        self
+    }
+
+    /// Renders the page for `route` into the shell
+    pub fn render(&self, route: &R) -> String {
+        let (model, view) = (self.page)(route);
+        let mut scripts = String::new();
+        if let Some(script) = &self.hydration {
+            if let Some(state) = &self.state {
+                scripts.push_str(r#"<script type="application/json" id="oak-state">"#);
+                // `<` is escaped so the state can never close the script tag
+                scripts.push_str(&state(&model).replace('<', "\\u003c"));
+                scripts.push_str("</script>");
+            }
+            scripts.push_str(&format!(
+                r#"<script type="module" src="{}"></script>"#,
+                ssr::escape_attribute(script)
+            ));
+        }
+        fill(
+            &self.shell,
+            &[
+                ("{{head}}", &self.head.join("")),
+                ("{{scripts}}", &scripts),
+                ("{{body}}", &ssr::render_to_string(&view)),
+            ],
+        )
+    }
+
+    /// Writes the `index.html` of every route, and returns the paths that were written
+    pub fn build(&self) -> io::Result<Vec<PathBuf>> {
+        let mut written = Vec::with_capacity(self.routes.len());
+        for route in &self.routes {
+            let dir = self.out_dir.join(relative_path(&route.path())?);
+            fs::create_dir_all(&dir)?;
+            let file = dir.join("index.html");
+            fs::write(&file, self.render(route))?;
+            written.push(file);
+        }
+        Ok(written)
+    }
+}
+
+/// Reads the model a page was rendered with, as embedded by `Site::state` with a serializer
+/// that writes JSON, like `serde_json::to_string`. Returns `None` when the page has no state.
+///
+/// ```no_run
+/// let count: i32 = oak::ssg::read_state().unwrap_or(None).unwrap_or(0);
+/// ```
+#[cfg(feature = "browser")]
+pub fn read_state<Model>() -> Result<Option<Model>, serde_json::Error>
+where
+    Model: serde::de::DeserializeOwned,
+{
+    let state = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id("oak-state"))
+        .and_then(|element| element.text_content());
+    match state {
+        Some(state) => serde_json::from_str(&state).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Replaces the placeholders of `shell` in one pass, so that placeholders which end up in a
+/// replacement, like in the state or the body, are left alone
+fn fill(shell: &str, values: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(shell.len());
+    let mut rest = shell;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match values
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                output.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                output.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Turns a route path like `/blog/first-post/` into a path relative to the output directory.
+/// Paths that would escape the output directory are rejected.
+fn relative_path(path: &str) -> io::Result<PathBuf> {
+    let relative = Path::new(path.trim_start_matches('/'));
+    for component in relative.components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("the route path {:?} is outside of the site", path),
+                ));
+            }
+        }
+    }
+    Ok(relative.to_owned())
+}
+
+const USAGE: &str = "Usage: [--out <dir>]
+
+Options:
+    --out <dir>    Where to write the site, instead of the configured directory
+    --help         Show this message";
+
+/// Builds `site` from a binary, taking the output directory from the command line. Every
+/// written file is printed, and the process exits with an error when the build fails.
+///
+/// ```no_run
+/// # use oak::html::{h1, Html};
+/// # fn page(route: &&str) -> ((), Html<()>) { ((), h1([], [*route])) }
+/// fn main() {
+///     oak::ssg::main(oak::ssg::Site::new(vec!["/"], page));
+/// }
+/// ```
+pub fn main<R: Route, Model, Msg>(mut site: Site<R, Model, Msg>) {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => match args.next() {
+                Some(out_dir) => site = site.out_dir(out_dir),
+                None => exit_with_usage("--out needs a directory"),
+            },
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => exit_with_usage(&format!("unknown argument {:?}", arg)),
+        }
+    }
+
+    match site.build() {
+        Ok(written) => {
+            for file in written {
+                println!("{}", file.display());
+            }
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!("error: {}\n\n{}", error, USAGE);
+    std::process::exit(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeholders_but_not_those_in_replacements() {
+        let values = [
+            ("{{head}}", "<title>{{body}}</title>"),
+            ("{{body}}", "{{head}}"),
+        ];
+        assert_eq!(
+            fill("{{head}}|{{body}}", &values),
+            "<title>{{body}}</title>|{{head}}"
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_placeholders_and_stray_braces_alone() {
+        let values = [("{{body}}", "page")];
+        assert_eq!(
+            fill("{{ {{other}} {{body}} {{", &values),
+            "{{ {{other}} page {{"
+        );
+    }
+
+    #[test]
+    fn turns_route_paths_into_relative_paths() {
+        assert_eq!(relative_path("/").unwrap(), PathBuf::from(""));
+        assert_eq!(
+            relative_path("/blog/first-post/").unwrap(),
+            PathBuf::from("blog/first-post")
+        );
+        assert_eq!(relative_path("./about").unwrap(), PathBuf::from("./about"));
+    }
+
+    #[test]
+    fn rejects_route_paths_outside_of_the_site() {
+        for path in &["..", "/../secret", "/blog/../../secret"] {
+            let error = relative_path(path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{}", path);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Renders `Html` to a string of HTML, without a browser. This is what static sites and
+//! server rendering are built on.
+//!
+//! ```
+//! use oak::html::{attributes::class, br, div, text, Html};
+//!
+//! let view: Html<()> = div([class("greeting")], [text("Tom & Jerry"), br([])]);
+//! assert_eq!(
+//!     oak::ssr::render_to_string(&view),
+//!     r#"<div class="greeting">Tom &amp; Jerry<br></div>"#
+//! );
+//! ```
+
+use crate::html::{Attribute, Children, Element, Html, Value};
+
+pub fn render_to_string<Msg>(html: &Html<Msg>) -> String {
+    let mut output = String::new();
+    write_html(&mut output, html);
+    output
+}
+
+/// Appends the HTML for `html` to `output`
+pub fn write_html<Msg>(output: &mut String, html: &Html<Msg>) {
+    match html {
+        Html::Element(element) => write_element(output, element),
+        Html::Text(text) => output.push_str(&escape_text(text)),
+        Html::Map(mapped) => mapped.0.write_html(output),
+        Html::Fragment(children) => {
+            for child in children {
+                write_html(output, child);
+            }
+        }
+        Html::None => {}
+        Html::Lazy(lazy) => write_html(output, &lazy.0.view()),
+    }
+}
+
+fn write_element<Msg>(output: &mut String, element: &Element<Msg>) {
+    output.push('<');
+    output.push_str(&element.name);
+    write_attributes(output, &element.name, &element.attrs);
+    output.push('>');
+
+    let children = match &element.children {
+        // Void elements, like `br` or `input`, have no closing tag
+        Children::SelfClosing => return,
+        Children::Nodes(children) => children,
+    };
+
+    // The contents of a text area are its value
+    let value = element.attrs.iter().rev().find_map(|attr| match attr {
+        Attribute::Property(name, Value::String(value)) if name == "value" => Some(value),
+        _ => None,
+    });
+    match value {
+        Some(value) if element.name == "textarea" => output.push_str(&escape_text(value)),
+        _ => {
+            for child in children {
+                match child {
+                    // Scripts and styles are not parsed as HTML, so escaping would corrupt them
+                    Html::Text(text) if is_raw_text(&element.name) => {
+                        output.push_str(&escape_raw_text(text))
+                    }
+                    child => write_html(output, child),
+                }
+            }
+        }
+    }
+
+    output.push_str("</");
+    output.push_str(&element.name);
+    output.push('>');
+}
+
+fn write_attributes<Msg>(output: &mut String, element: &str, attrs: &[Attribute<Msg>]) {
+    let mut styles: Vec<(&str, &str)> = vec![];
+    for attr in attrs {
+        match attr {
+            Attribute::Text(name, value) | Attribute::Namespaced(_, name, value) => {
+                write_attribute(output, name, Some(value))
+            }
+            Attribute::Bool(name, true) => write_attribute(output, name, None),
+            // The value of a text area is written as its contents instead
+            Attribute::Property(name, _) if element == "textarea" && name == "value" => {}
+            Attribute::Property(name, value) => {
+                let name = match property_to_attribute(name) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                match value {
+                    Value::Bool(true) => write_attribute(output, &name, None),
+                    Value::String(value) => write_attribute(output, &name, Some(value)),
+                    Value::Number(value) => {
+                        write_attribute(output, &name, Some(&value.to_string()))
+                    }
+                    Value::Bool(false) | Value::Null => {}
+                }
+            }
+            Attribute::Style(properties) => {
+                for (name, value) in properties {
+                    styles.retain(|(existing, _)| existing != name);
+                    styles.push((name, value));
+                }
+            }
+            // Keys and event listeners only matter to the browser renderer
+            Attribute::Bool(_, false) | Attribute::Key(_) | Attribute::Event(_) => {}
+        }
+    }
+
+    if !styles.is_empty() {
+        let style = styles
+            .iter()
+            .map(|(name, value)| format!("{}: {};", name, value))
+            .collect::<Vec<_>>()
+            .join(" ");
+        write_attribute(output, "style", Some(&style));
+    }
+}
+
+fn write_attribute(output: &mut String, name: &str, value: Option<&str>) {
+    output.push(' ');
+    output.push_str(name);
+    if let Some(value) = value {
+        output.push_str("=\"");
+        output.push_str(&escape_attribute(value));
+        output.push('"');
+    }
+}
+
+/// The attribute that reflects a DOM property in markup, if there is one
+fn property_to_attribute(property: &str) -> Option<String> {
+    match property {
+        "className" => Some("class".to_owned()),
+        "htmlFor" => Some("for".to_owned()),
+        "indeterminate" => None,
+        _ => Some(property.to_lowercase()),
+    }
+}
+
+fn is_raw_text(name: &str) -> bool {
+    name == "script" || name == "style"
+}
+
+/// Keeps the contents of a script or style from ending the element early. Neither `</`
+/// nor `<!--` can appear in them as is, and `<\/` and `<\!--` mean the same in JavaScript
+/// strings and CSS.
+fn escape_raw_text(text: &str) -> String {
+    text.replace("</", "<\\/").replace("<!--", "<\\!--")
+}
+
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::attributes::{checked, disabled, hidden, href, key, required, value};
+    use crate::html::events::on_click;
+    use crate::html::{a, br, div, input, text, textarea};
+
+    fn element(name: &str, children: Vec<Html<()>>) -> Html<()> {
+        Html::Element(Element {
+            name: name.to_owned(),
+            namespace: None,
+            attrs: vec![],
+            children: Children::Nodes(children),
+        })
+    }
+
+    #[test]
+    fn escapes_attribute_values() {
+        let view: Html<()> = a([href(r#"/?q="tom"&'jerry'<3"#)], [text("link")]);
+        assert_eq!(
+            render_to_string(&view),
+            r#"<a href="/?q=&quot;tom&quot;&amp;&#39;jerry&#39;&lt;3">link</a>"#
+        );
+    }
+
+    #[test]
+    fn writes_void_elements_without_a_closing_tag() {
+        let view: Html<()> = div([], [br([]), input([])]);
+        assert_eq!(render_to_string(&view), "<div><br><input></div>");
+    }
+
+    #[test]
+    fn writes_true_booleans_as_bare_attributes() {
+        let view: Html<()> = input([
+            required(true),
+            hidden(false),
+            checked(true),
+            disabled(false),
+        ]);
+        assert_eq!(render_to_string(&view), "<input required checked>");
+    }
+
+    #[test]
+    fn keeps_scripts_and_styles_from_ending_early() {
+        let script = element("script", vec![text("let a = '</script><!-- & -->';")]);
+        assert_eq!(
+            render_to_string(&script),
+            r"<script>let a = '<\/script><\!-- & -->';</script>"
+        );
+        let style = element("style", vec![text("a::after { content: '</style>' }")]);
+        assert_eq!(
+            render_to_string(&style),
+            r"<style>a::after { content: '<\/style>' }</style>"
+        );
+    }
+
+    #[test]
+    fn writes_the_value_of_a_text_area_as_its_contents() {
+        let view: Html<()> = textarea([value("a < b")], [text("ignored")]);
+        assert_eq!(render_to_string(&view), "<textarea>a &lt; b</textarea>");
+    }
+
+    #[test]
+    fn leaves_out_keys_and_listeners() {
+        let view: Html<()> = div([key("item"), on_click(())], [text("item")]);
+        assert_eq!(render_to_string(&view), "<div>item</div>");
+    }
+}
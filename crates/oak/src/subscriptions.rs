@@ -0,0 +1,339 @@
+use crate::platform::{Dispatcher, Remap, Sub, Subs, Subscription};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+
+/// Keeps track of the subscriptions a program is currently listening to.
+pub struct SubscriptionManager {
+    active: RefCell<HashMap<String, Active>>,
+}
+
+/// A running subscription, with the mappers its messages go through
+struct Active {
+    subscription: Subscription,
+    mappers: Vec<Rc<dyn Remap>>,
+}
+
+impl SubscriptionManager {
+    pub fn new() -> Self {
+        SubscriptionManager {
+            active: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Diffs `sub` against the subscriptions that are currently running. Subscriptions that
+    /// are gone are torn down and new ones are started with `dispatcher`. The ones that are
+    /// kept map their messages with the mappers of `sub` from now on.
+    pub fn update<Msg>(
+        &self,
+        sub: &dyn Sub<Msg>,
+        dispatcher: &Dispatcher<Msg>,
+    ) -> Result<(), JsValue> {
+        let mut subs = Subs::new();
+        sub.collect(&mut subs);
+
+        let mut keys = HashSet::new();
+        let mut to_start = vec![];
+        for entry in subs.into_entries() {
+            if !keys.insert(entry.key.clone()) {
+                continue;
+            }
+            match self.active.borrow().get(&entry.key) {
+                Some(active) => {
+                    for (running, latest) in active.mappers.iter().zip(&entry.mappers) {
+                        running.remap(&**latest);
+                    }
+                }
+                None => to_start.push(entry),
+            }
+        }
+
+        let removed = {
+            let mut active = self.active.borrow_mut();
+            let stale = active
+                .keys()
+                .filter(|key| !keys.contains(*key))
+                .cloned()
+                .collect::<Vec<_>>();
+            let removed = stale
+                .iter()
+                .filter_map(|key| active.remove(key))
+                .collect::<Vec<_>>();
+            // Reserve the keys before starting anything. A subscription may dispatch while it
+            // starts, and the nested update should not start it a second time.
+            for entry in &to_start {
+                active.insert(
+                    entry.key.clone(),
+                    Active {
+                        subscription: Subscription::none(),
+                        mappers: entry.mappers.clone(),
+                    },
+                );
+            }
+            removed
+        };
+        // Tear down outside of the borrow, in case a teardown ends up dispatching
+        drop(removed);
+
+        let mut to_start = to_start.into_iter();
+        while let Some(entry) = to_start.next() {
+            let key = entry.key;
+            let subscription = match (entry.start)(dispatcher) {
+                Ok(subscription) => subscription,
+                Err(error) => {
+                    // Give up the reserved keys, so that the next update tries again
+                    let mut active = self.active.borrow_mut();
+                    active.remove(&key);
+                    for entry in to_start.by_ref() {
+                        active.remove(&entry.key);
+                    }
+                    return Err(error);
+                }
+            };
+            let mut active = self.active.borrow_mut();
+            if let Some(slot) = active.get_mut(&key) {
+                slot.subscription = subscription;
+            } else {
+                // A nested update already removed this subscription again
+                drop(active);
+                drop(subscription);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+    type Hook = Rc<dyn Fn(&str, &Dispatcher<()>) -> Result<(), JsValue>>;
+
+    /// A subscription for every key, which logs when it starts and stops. `hook` runs as a
+    /// key starts, and can fail it.
+    struct Keys {
+        keys: Vec<&'static str>,
+        log: Log,
+        hook: Hook,
+    }
+
+    fn keys(keys: &[&'static str], log: &Log) -> Keys {
+        keys_with(keys, log, Rc::new(|_, _| Ok(())))
+    }
+
+    fn keys_with(keys: &[&'static str], log: &Log, hook: Hook) -> Keys {
+        Keys {
+            keys: keys.to_vec(),
+            log: log.clone(),
+            hook,
+        }
+    }
+
+    impl Sub<()> for Keys {
+        fn collect(&self, subs: &mut Subs<()>) {
+            for key in &self.keys {
+                let key = *key;
+                let log = self.log.clone();
+                let hook = self.hook.clone();
+                subs.add(key.to_owned(), move |dispatcher| {
+                    hook(key, dispatcher)?;
+                    log.borrow_mut().push(format!("start {}", key));
+                    Ok(Subscription::new(move || {
+                        log.borrow_mut().push(format!("stop {}", key))
+                    }))
+                });
+            }
+        }
+    }
+
+    fn dispatcher() -> Dispatcher<()> {
+        Dispatcher::new(|_| {})
+    }
+
+    fn take(log: &Log) -> Vec<String> {
+        log.borrow_mut().drain(..).collect()
+    }
+
+    fn active(manager: &SubscriptionManager) -> Vec<String> {
+        let mut keys = manager.active.borrow().keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn starts_new_keys_once_and_tears_down_missing_ones() {
+        let log = Log::default();
+        let manager = SubscriptionManager::new();
+        manager
+            .update(&keys(&["a", "b", "a"], &log), &dispatcher())
+            .unwrap();
+        assert_eq!(take(&log), vec!["start a", "start b"]);
+
+        manager
+            .update(&keys(&["b", "c"], &log), &dispatcher())
+            .unwrap();
+        assert_eq!(take(&log), vec!["stop a", "start c"]);
+        assert_eq!(active(&manager), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn releases_the_keys_that_did_not_start() {
+        let log = Log::default();
+        let manager = SubscriptionManager::new();
+        let failing: Hook = Rc::new(|key, _| match key {
+            "b" => Err(JsValue::NULL),
+            _ => Ok(()),
+        });
+        let result = manager.update(&keys_with(&["a", "b", "c"], &log, failing), &dispatcher());
+        assert!(result.is_err());
+        assert_eq!(active(&manager), vec!["a"]);
+
+        manager
+            .update(&keys(&["a", "b", "c"], &log), &dispatcher())
+            .unwrap();
+        assert_eq!(take(&log), vec!["start a", "start b", "start c"]);
+    }
+
+    #[test]
+    fn tears_down_outside_of_the_borrow() {
+        let log = Log::default();
+        let manager = Rc::new(SubscriptionManager::new());
+        let mut subs = Subs::new();
+        let nested = manager.clone();
+        let nested_log = log.clone();
+        subs.add("a".to_owned(), move |_| {
+            Ok(Subscription::new(move || {
+                // Like a teardown that ends up dispatching
+                nested
+                    .update(&keys(&["b"], &nested_log), &dispatcher())
+                    .unwrap()
+            }))
+        });
+        struct Once(RefCell<Option<Subs<()>>>);
+        impl Sub<()> for Once {
+            fn collect(&self, subs: &mut Subs<()>) {
+                if let Some(once) = self.0.borrow_mut().take() {
+                    *subs = once;
+                }
+            }
+        }
+        manager
+            .update(&Once(RefCell::new(Some(subs))), &dispatcher())
+            .unwrap();
+        manager.update(&keys(&[], &log), &dispatcher()).unwrap();
+        assert_eq!(take(&log), vec!["start b"]);
+        assert_eq!(active(&manager), vec!["b"]);
+    }
+
+    #[test]
+    fn does_not_start_a_key_twice_from_a_nested_update() {
+        let log = Log::default();
+        let manager = Rc::new(SubscriptionManager::new());
+        let nested = manager.clone();
+        let nested_log = log.clone();
+        let hook: Hook = Rc::new(move |key, dispatcher| {
+            if key == "a" {
+                nested.update(&keys(&["a", "b"], &nested_log), dispatcher)?;
+            }
+            Ok(())
+        });
+        manager
+            .update(&keys_with(&["a"], &log, hook), &dispatcher())
+            .unwrap();
+        assert_eq!(take(&log), vec!["start b", "start a"]);
+        assert_eq!(active(&manager), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn drops_a_subscription_a_nested_update_removed_while_it_started() {
+        let log = Log::default();
+        let manager = Rc::new(SubscriptionManager::new());
+        let nested = manager.clone();
+        let nested_log = log.clone();
+        let hook: Hook = Rc::new(move |key, dispatcher| {
+            if key == "a" {
+                nested.update(&keys(&[], &nested_log), dispatcher)?;
+            }
+            Ok(())
+        });
+        manager
+            .update(&keys_with(&["a"], &log, hook), &dispatcher())
+            .unwrap();
+        assert_eq!(take(&log), vec!["start a", "stop a"]);
+        assert!(active(&manager).is_empty());
+
+        manager.update(&keys(&["a"], &log), &dispatcher()).unwrap();
+        assert_eq!(take(&log), vec!["start a"]);
+    }
+
+    /// A subscription that hands its dispatcher over, so that a test can send messages
+    struct Source(Rc<RefCell<Vec<Dispatcher<()>>>>);
+
+    impl Sub<()> for Source {
+        fn collect(&self, subs: &mut Subs<()>) {
+            let started = self.0.clone();
+            subs.add("source".to_owned(), move |dispatcher| {
+                started.borrow_mut().push(dispatcher.clone());
+                Ok(Subscription::none())
+            });
+        }
+    }
+
+    /// The subscription of the item at `index` of a list, mapped by the same closure for
+    /// every item
+    fn item(source: &Source, index: usize) -> impl Sub<usize> {
+        Source(source.0.clone()).map(move |()| index)
+    }
+
+    fn recorder() -> (Dispatcher<usize>, Rc<RefCell<Vec<usize>>>) {
+        let messages = Rc::new(RefCell::new(vec![]));
+        let received = messages.clone();
+        let dispatcher = Dispatcher::new(move |message| received.borrow_mut().push(message));
+        (dispatcher, messages)
+    }
+
+    #[test]
+    fn maps_messages_with_the_mapper_of_the_latest_update() {
+        let source = Source(Rc::default());
+        let (dispatcher, messages) = recorder();
+        let manager = SubscriptionManager::new();
+        manager.update(&item(&source, 1), &dispatcher).unwrap();
+        manager.update(&item(&source, 2), &dispatcher).unwrap();
+        // Mapped once more, as in a parent of the list
+        let parent = |index| item(&source, index).map(|index| index * 10);
+        manager.update(&parent(3), &dispatcher).unwrap();
+        manager.update(&parent(4), &dispatcher).unwrap();
+
+        let started = source.0.borrow().clone();
+        assert_eq!(started.len(), 2);
+        started[0].dispatch(());
+        started[1].dispatch(());
+        assert_eq!(*messages.borrow(), vec![2, 40]);
+    }
+
+    #[test]
+    fn starts_a_subscription_for_every_item_mapped_by_the_same_closure() {
+        let source = Source(Rc::default());
+        let (dispatcher, messages) = recorder();
+        let manager = SubscriptionManager::new();
+        let items = |indexes: &[usize]| {
+            let items = indexes.iter().map(|index| item(&source, *index));
+            crate::platform::batch(items.collect())
+        };
+        manager.update(&items(&[0, 1]), &dispatcher).unwrap();
+        let started = source.0.borrow().clone();
+        assert_eq!(started.len(), 2);
+        started[0].dispatch(());
+        started[1].dispatch(());
+        assert_eq!(messages.borrow_mut().split_off(0), vec![0, 1]);
+
+        // The first item is removed, and the subscription of the second one moves up
+        manager.update(&items(&[1]), &dispatcher).unwrap();
+        assert_eq!(active(&manager).len(), 1);
+        started[0].dispatch(());
+        assert_eq!(*messages.borrow(), vec![1]);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::html::Attribute;
+use crate::svg::XLINK_NAMESPACE;
+
+macro_rules! declare_text_attributes {
+    ($($x:ident, $tag:expr)*) => ($(
+        pub fn $x<Msg>(value: &str) -> Attribute<Msg> {
+            Attribute::Text($tag.to_owned(), value.to_owned())
+        }
+    )*);
+
+    ($($x:ident)*) => ($(
+        declare_text_attributes!($x, stringify!($x));
+    )*);
+}
+
+/// The target of a link or `use` element, for renderers that don't support the plain
+/// `href` of SVG 2
+pub fn xlink_href<Msg>(value: &str) -> Attribute<Msg> {
+    Attribute::Namespaced(
+        XLINK_NAMESPACE.to_owned(),
+        "xlink:href".to_owned(),
+        value.to_owned(),
+    )
+}
+
+declare_text_attributes! {
+    width
+    height
+    x
+    y
+    x1
+    y1
+    x2
+    y2
+    cx
+    cy
+    r
+    rx
+    ry
+    dx
+    dy
+    d
+    points
+    fill
+    stroke
+    opacity
+    transform
+    offset
+    mask
+    visibility
+    href
+}
+
+declare_text_attributes! {
+    view_box, "viewBox"
+    preserve_aspect_ratio, "preserveAspectRatio"
+    path_length, "pathLength"
+    gradient_units, "gradientUnits"
+    gradient_transform, "gradientTransform"
+    pattern_units, "patternUnits"
+    std_deviation, "stdDeviation"
+    fill_opacity, "fill-opacity"
+    fill_rule, "fill-rule"
+    stroke_width, "stroke-width"
+    stroke_linecap, "stroke-linecap"
+    stroke_linejoin, "stroke-linejoin"
+    stroke_dasharray, "stroke-dasharray"
+    stroke_dashoffset, "stroke-dashoffset"
+    stroke_opacity, "stroke-opacity"
+    stop_color, "stop-color"
+    stop_opacity, "stop-opacity"
+    clip_path, "clip-path"
+    marker_start, "marker-start"
+    marker_mid, "marker-mid"
+    marker_end, "marker-end"
+    font_family, "font-family"
+    font_size, "font-size"
+    font_weight, "font-weight"
+    text_anchor, "text-anchor"
+    dominant_baseline, "dominant-baseline"
+}
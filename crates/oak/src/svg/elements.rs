@@ -0,0 +1,71 @@
+use crate::html::{Attribute, Children, Element, Html};
+use crate::svg::NAMESPACE;
+
+macro_rules! declare_elements {
+    ($($x:ident, $tag:expr)*) => ($(
+        pub fn $x<Msg: Clone, A: AsRef<[Attribute<Msg>]>, H: Into<Html<Msg>> + Clone, C: AsRef<[H]>>(
+            attrs: A,
+            children: C,
+        ) -> Html<Msg> {
+            Html::Element(Element {
+                name: $tag.to_owned(),
+                namespace: Some(NAMESPACE.to_owned()),
+                children: Children::Nodes(children.as_ref().to_vec().into_iter().map(|c| c.into()).collect()),
+                attrs: attrs.as_ref().to_vec(),
+            })
+        }
+    )*);
+
+    ($($x:ident)*) => ($(
+        declare_elements!($x, stringify!($x));
+    )*);
+}
+
+declare_elements! {
+    svg
+    g
+    defs
+    symbol
+    title
+    desc
+    metadata
+    switch
+    a
+    circle
+    ellipse
+    line
+    path
+    polygon
+    polyline
+    rect
+    text
+    tspan
+    image
+    stop
+    pattern
+    mask
+    marker
+    filter
+    animate
+    set
+    view
+    style
+}
+
+declare_elements! {
+    use_, "use"
+    text_path, "textPath"
+    foreign_object, "foreignObject"
+    linear_gradient, "linearGradient"
+    radial_gradient, "radialGradient"
+    clip_path, "clipPath"
+    animate_motion, "animateMotion"
+    animate_transform, "animateTransform"
+    fe_blend, "feBlend"
+    fe_color_matrix, "feColorMatrix"
+    fe_flood, "feFlood"
+    fe_gaussian_blur, "feGaussianBlur"
+    fe_merge, "feMerge"
+    fe_merge_node, "feMergeNode"
+    fe_offset, "feOffset"
+}
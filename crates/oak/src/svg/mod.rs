@@ -0,0 +1,31 @@
+//! SVG elements and attributes. Elements are created in the SVG namespace, so they render as
+//! graphics and not as unknown HTML elements.
+//!
+//! ```
+//! use oak::html::Html;
+//! use oak::svg::{attributes::*, circle, svg, title};
+//!
+//! let chart: Html<()> = svg(
+//!     [view_box("0 0 10 10")],
+//!     [circle(
+//!         [cx("5"), cy("5"), r("4"), fill("teal")],
+//!         [title([], ["Done"])],
+//!     )],
+//! );
+//! assert_eq!(
+//!     oak::ssr::render_to_string(&chart),
+//!     r#"<svg viewBox="0 0 10 10"><circle cx="5" cy="5" r="4" fill="teal"><title>Done</title></circle></svg>"#
+//! );
+//! ```
+//!
+//! Note that `text` is the SVG `<text>` element here, not `oak::html::text`.
+
+pub mod attributes;
+
+mod elements;
+pub use self::elements::*;
+
+pub const NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// The namespace of `xlink:` attributes
+pub const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
@@ -1,14 +1,21 @@
-use crate::platform::Sub;
+use crate::platform::{Cmd, Dispatcher, Sub, Subs, Subscription};
 pub use std::time::Duration;
-use wasm_bindgen::prelude::JsValue;
+use wasm_bindgen::prelude::{Closure, JsValue};
+use wasm_bindgen::JsCast;
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Time(f64);
 
 impl Time {
+    /// Reads the clock. Programs should prefer the `now` command so that `update` stays pure.
     pub fn now() -> Self {
         Time(js_sys::Date::now())
     }
+
+    /// Milliseconds since the Unix epoch
+    pub fn millis(&self) -> f64 {
+        self.0
+    }
 }
 
 impl ToString for Time {
@@ -19,12 +26,53 @@ impl ToString for Time {
     }
 }
 
-// pub fn every<Msg>(duration: Duration, Fn() -> Msg) -> Sub<Msg> {
-//     let cb2 = Closure::wrap(Box::new(move || {
-//         web_sys::console::log_1(&JsValue::from("Balls"));
-//     }) as Box<dyn Fn()>);
-//     window
-//         .set_interval_with_callback_and_timeout_and_arguments_0(cb2.as_ref().unchecked_ref(), 1_000)
-//         .unwrap();
-//     cb2.forget();
-// }
+/// A command that reads the current time and hands it to `to_msg`.
+pub fn now<Msg>(to_msg: fn(Time) -> Msg) -> Now<Msg> {
+    Now { to_msg }
+}
+
+pub struct Now<Msg> {
+    to_msg: fn(Time) -> Msg,
+}
+
+impl<Msg> Cmd<Msg> for Now<Msg> {
+    fn run(&self, dispatcher: &Dispatcher<Msg>) -> Result<(), JsValue> {
+        dispatcher.dispatch((self.to_msg)(Time::now()));
+        Ok(())
+    }
+}
+
+/// A subscription that sends the current time every `duration`.
+pub fn every<Msg>(duration: Duration, to_msg: fn(Time) -> Msg) -> Every<Msg> {
+    Every { duration, to_msg }
+}
+
+pub struct Every<Msg> {
+    duration: Duration,
+    to_msg: fn(Time) -> Msg,
+}
+
+impl<Msg: 'static> Sub<Msg> for Every<Msg> {
+    fn collect(&self, subs: &mut Subs<Msg>) {
+        let millis = self.duration.as_millis() as i32;
+        let to_msg = self.to_msg;
+        subs.add(
+            format!("time::every/{}/{:p}", millis, to_msg),
+            move |dispatcher| {
+                let dispatcher = dispatcher.clone();
+                let callback = Closure::wrap(Box::new(move || {
+                    dispatcher.dispatch(to_msg(Time::now()));
+                }) as Box<dyn Fn()>);
+                let window = web_sys::window().expect("no global `window` exists");
+                let handle = window.set_interval_with_callback_and_timeout_and_arguments_0(
+                    callback.as_ref().unchecked_ref(),
+                    millis,
+                )?;
+                Ok(Subscription::new(move || {
+                    window.clear_interval_with_handle(handle);
+                    drop(callback);
+                }))
+            },
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use crate::platform::Subscription;
+use crate::websocket::{Backend, Message, Socket, WsEvent};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::prelude::JsValue;
+
+/// A backend for tests, which stands in for the server of every socket. Sockets only open,
+/// receive messages or close when the test says so, and reconnects wait for the test to
+/// move the mock's virtual clock with `advance`.
+///
+/// ```
+/// use oak::platform::Subs;
+/// use oak::websocket::{self, Message, MockBackend, WsEvent};
+/// use oak::{Cmd, Dispatcher, Sub};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use std::time::Duration;
+///
+/// let mock = MockBackend::new();
+/// websocket::set_backend(mock.clone());
+///
+/// let events = Rc::new(RefCell::new(vec![]));
+/// let received = events.clone();
+/// let dispatcher = Dispatcher::new(move |event| received.borrow_mut().push(event));
+/// let mut subs = Subs::new();
+/// websocket::listen("wss://chat", |event| event).collect(&mut subs);
+/// let _subscriptions = subs.start(&dispatcher).unwrap();
+///
+/// websocket::send("wss://chat", Message::Text("Hi".to_owned()))
+///     .run(&dispatcher)
+///     .unwrap();
+/// mock.open("wss://chat");
+/// assert_eq!(mock.sent("wss://chat"), vec![Message::Text("Hi".to_owned())]);
+///
+/// mock.close("wss://chat", 1006, "");
+/// mock.advance(Duration::from_secs(1));
+/// assert_eq!(mock.connections("wss://chat"), 2);
+/// assert_eq!(events.borrow()[0], WsEvent::Open);
+/// ```
+#[derive(Clone, Default)]
+pub struct MockBackend(Rc<RefCell<State>>);
+
+#[derive(Default)]
+struct State {
+    sockets: Vec<MockSocket>,
+    timers: Vec<Timer>,
+    now: Duration,
+    next_timer: usize,
+    refused: HashSet<String>,
+}
+
+struct MockSocket {
+    url: String,
+    events: Rc<dyn Fn(WsEvent)>,
+    sent: Vec<Message>,
+    closed: bool,
+}
+
+struct Timer {
+    id: usize,
+    due: Duration,
+    callback: Box<dyn FnOnce()>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        MockBackend::default()
+    }
+
+    /// How many sockets to `url` were opened so far, counting reconnects
+    pub fn connections(&self, url: &str) -> usize {
+        self.0
+            .borrow()
+            .sockets
+            .iter()
+            .filter(|socket| socket.url == url)
+            .count()
+    }
+
+    /// Whether there is a socket to `url` that is not closed
+    pub fn is_connected(&self, url: &str) -> bool {
+        self.0
+            .borrow()
+            .sockets
+            .iter()
+            .any(|socket| socket.url == url && !socket.closed)
+    }
+
+    /// Every message sent to `url` so far, over all of its sockets
+    pub fn sent(&self, url: &str) -> Vec<Message> {
+        self.0
+            .borrow()
+            .sockets
+            .iter()
+            .filter(|socket| socket.url == url)
+            .flat_map(|socket| socket.sent.iter().cloned())
+            .collect()
+    }
+
+    /// Whether connecting to `url` fails from now on, like when its server is down
+    pub fn set_refusing(&self, url: &str, refusing: bool) {
+        let refused = &mut self.0.borrow_mut().refused;
+        if refusing {
+            refused.insert(url.to_owned());
+        } else {
+            refused.remove(url);
+        }
+    }
+
+    /// Accepts the socket to `url`
+    pub fn open(&self, url: &str) {
+        self.emit(url, WsEvent::Open, false)
+    }
+
+    /// Sends `message` from the server to `url`
+    pub fn receive(&self, url: &str, message: Message) {
+        self.emit(url, WsEvent::Message(message), false)
+    }
+
+    /// Closes the socket to `url` from the server side
+    pub fn close(&self, url: &str, code: u16, reason: &str) {
+        let event = WsEvent::Close {
+            code,
+            reason: reason.to_owned(),
+        };
+        self.emit(url, event, true)
+    }
+
+    pub fn error(&self, url: &str) {
+        self.emit(url, WsEvent::Error, false)
+    }
+
+    /// Moves the virtual clock forward by `duration`, and runs the reconnects that are due
+    /// by then in order
+    pub fn advance(&self, duration: Duration) {
+        let until = self.0.borrow().now + duration;
+        loop {
+            let timer = {
+                let mut state = self.0.borrow_mut();
+                let next = state
+                    .timers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, timer)| timer.due <= until)
+                    .min_by_key(|(_, timer)| (timer.due, timer.id))
+                    .map(|(index, _)| index);
+                match next {
+                    Some(index) => {
+                        let timer = state.timers.remove(index);
+                        state.now = timer.due;
+                        timer
+                    }
+                    None => break,
+                }
+            };
+            (timer.callback)();
+        }
+        self.0.borrow_mut().now = until;
+    }
+
+    /// Reports `event` on the open socket to `url`, if there is one
+    fn emit(&self, url: &str, event: WsEvent, close: bool) {
+        let events = {
+            let mut state = self.0.borrow_mut();
+            let socket = state
+                .sockets
+                .iter_mut()
+                .rev()
+                .find(|socket| socket.url == url && !socket.closed);
+            match socket {
+                Some(socket) => {
+                    socket.closed = close;
+                    socket.events.clone()
+                }
+                None => return,
+            }
+        };
+        // The state is not borrowed here, as the message may send more messages
+        events(event)
+    }
+}
+
+impl Backend for MockBackend {
+    fn connect(&self, url: &str, events: Box<dyn Fn(WsEvent)>) -> Result<Box<dyn Socket>, JsValue> {
+        let mut state = self.0.borrow_mut();
+        if state.refused.contains(url) {
+            // Other values need a JavaScript engine, which tests may not have
+            return Err(JsValue::NULL);
+        }
+        state.sockets.push(MockSocket {
+            url: url.to_owned(),
+            events: events.into(),
+            sent: vec![],
+            closed: false,
+        });
+        Ok(Box::new(SocketHandle {
+            backend: self.clone(),
+            index: state.sockets.len() - 1,
+        }))
+    }
+
+    fn schedule(
+        &self,
+        delay: Duration,
+        callback: Box<dyn FnOnce()>,
+    ) -> Result<Subscription, JsValue> {
+        let mut state = self.0.borrow_mut();
+        state.next_timer += 1;
+        let id = state.next_timer;
+        let due = state.now + delay;
+        state.timers.push(Timer { id, due, callback });
+        let backend = self.clone();
+        Ok(Subscription::new(move || {
+            backend.0.borrow_mut().timers.retain(|timer| timer.id != id)
+        }))
+    }
+}
+
+struct SocketHandle {
+    backend: MockBackend,
+    index: usize,
+}
+
+impl Socket for SocketHandle {
+    fn send(&self, message: &Message) -> Result<(), JsValue> {
+        self.backend.0.borrow_mut().sockets[self.index]
+            .sent
+            .push(message.clone());
+        Ok(())
+    }
+
+    fn close(&self) {
+        self.backend.0.borrow_mut().sockets[self.index].closed = true;
+    }
+}
@@ -0,0 +1,557 @@
+//! WebSockets. The `listen` subscription keeps a socket to a URL open for as long as the
+//! program is subscribed, reconnecting with a backoff when the connection drops, and `send`
+//! is a command that sends a message on it.
+//!
+//! ```no_run
+//! use oak::websocket::{self, Message, WsEvent};
+//! use oak::{Cmd, Sub};
+//!
+//! enum Msg {
+//!     Chat(WsEvent),
+//! }
+//!
+//! fn subscriptions() -> impl Sub<Msg> {
+//!     websocket::listen("wss://example.com/chat", Msg::Chat)
+//! }
+//!
+//! fn say_hello() -> impl Cmd<Msg> {
+//!     websocket::send("wss://example.com/chat", Message::Text("Hello".to_owned()))
+//! }
+//! ```
+
+mod mock;
+mod web;
+
+pub use self::mock::MockBackend;
+pub use self::web::WebSocketBackend;
+use crate::platform::{Cmd, Dispatcher, Sub, Subs, Subscription};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+pub use std::time::Duration;
+use wasm_bindgen::prelude::JsValue;
+
+/// Something that happened to a socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum WsEvent {
+    Open,
+    Message(Message),
+    /// The connection was closed, which is followed by a reconnect unless the backoff ran
+    /// out of retries
+    Close {
+        code: u16,
+        reason: String,
+    },
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// How long to wait before reconnecting. The first retry waits `initial`, and every further
+/// one `factor` times as long as the one before, but never longer than `max`. A `factor`
+/// below zero, or one that is not a number, counts as 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub factor: f64,
+    /// How often to retry in a row before giving up, or `None` to keep retrying
+    pub retries: Option<u32>,
+}
+
+impl Backoff {
+    /// Never reconnects
+    pub fn never() -> Self {
+        Backoff {
+            retries: Some(0),
+            ..Backoff::default()
+        }
+    }
+
+    /// The delay before the retry after `attempt` failed ones, if there is one
+    fn delay(&self, attempt: u32) -> Option<Duration> {
+        if let Some(retries) = self.retries {
+            if attempt >= retries {
+                return None;
+            }
+        }
+        let factor = if self.factor >= 0.0 { self.factor } else { 1.0 };
+        let secs = self.initial.as_secs_f64() * factor.powi(attempt as i32);
+        // Also catches delays too long for a `Duration`, and zero times infinity
+        if secs < self.max.as_secs_f64() {
+            Some(Duration::from_secs_f64(secs))
+        } else {
+            Some(self.max)
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(30),
+            factor: 2.0,
+            retries: None,
+        }
+    }
+}
+
+/// A subscription to the events of a socket to `url`. Subscriptions to the same URL share
+/// one socket, which is closed when the last of them ends.
+pub fn listen<Msg>(url: &str, to_msg: fn(WsEvent) -> Msg) -> Listen<Msg> {
+    Listen {
+        url: url.to_owned(),
+        to_msg,
+        backoff: Backoff::default(),
+    }
+}
+
+/// A command that sends `message` on the socket to `url`. Messages sent while the socket
+/// is connecting are sent once it is open. Messages to URLs without a `listen` subscription,
+/// or whose socket ran out of retries, are dropped.
+pub fn send(url: &str, message: Message) -> Send {
+    Send {
+        url: url.to_owned(),
+        message,
+    }
+}
+
+pub struct Listen<Msg> {
+    url: String,
+    to_msg: fn(WsEvent) -> Msg,
+    backoff: Backoff,
+}
+
+impl<Msg> Listen<Msg> {
+    /// Reconnects with `backoff` instead of the default one. When several subscriptions
+    /// share a socket, the backoff of the first one is used.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+}
+
+impl<Msg: 'static> Sub<Msg> for Listen<Msg> {
+    fn collect(&self, subs: &mut Subs<Msg>) {
+        let url = self.url.clone();
+        let to_msg = self.to_msg;
+        let backoff = self.backoff.clone();
+        subs.add(
+            format!("websocket::listen/{}/{:p}", url, to_msg),
+            move |dispatcher| {
+                let dispatcher = dispatcher.clone();
+                start(
+                    url,
+                    backoff,
+                    Rc::new(move |event| dispatcher.dispatch(to_msg(event))),
+                )
+            },
+        );
+    }
+}
+
+pub struct Send {
+    url: String,
+    message: Message,
+}
+
+impl<Msg> Cmd<Msg> for Send {
+    fn run(&self, _: &Dispatcher<Msg>) -> Result<(), JsValue> {
+        CONNECTIONS.with(|connections| {
+            let mut connections = connections.borrow_mut();
+            let connection = match connections.get_mut(&self.url) {
+                Some(connection) if connection.gave_up => {
+                    log::warn!(
+                        "dropped a message to {}, which ran out of reconnects",
+                        self.url
+                    );
+                    return Ok(());
+                }
+                Some(connection) => connection,
+                None => {
+                    log::warn!(
+                        "dropped a message to {}, which has no websocket::listen subscription",
+                        self.url
+                    );
+                    return Ok(());
+                }
+            };
+            match &connection.socket {
+                Some(socket) if connection.open => socket.send(&self.message),
+                _ => {
+                    connection.queue.push(self.message.clone());
+                    Ok(())
+                }
+            }
+        })
+    }
+}
+
+/// Opens sockets for the subscriptions in this module. `WebSocketBackend` is the default,
+/// and tests without a browser use a `MockBackend` instead.
+pub trait Backend {
+    /// Opens a socket to `url`, which reports what happens to it to `events`
+    fn connect(&self, url: &str, events: Box<dyn Fn(WsEvent)>) -> Result<Box<dyn Socket>, JsValue>;
+
+    /// Calls `callback` after `delay`, unless the returned subscription is dropped first
+    fn schedule(
+        &self,
+        delay: Duration,
+        callback: Box<dyn FnOnce()>,
+    ) -> Result<Subscription, JsValue>;
+}
+
+/// A socket opened by a backend
+pub trait Socket {
+    fn send(&self, message: &Message) -> Result<(), JsValue>;
+
+    /// Closes the socket without reporting a `Close`
+    fn close(&self);
+}
+
+thread_local! {
+    static BACKEND: RefCell<Rc<dyn Backend>> = RefCell::new(Rc::new(WebSocketBackend));
+    static CONNECTIONS: RefCell<HashMap<String, Connection>> = RefCell::new(HashMap::new());
+}
+
+/// Opens the sockets of every subscription that starts after this with `backend`
+pub fn set_backend<B: Backend + 'static>(backend: B) {
+    BACKEND.with(|current| *current.borrow_mut() = Rc::new(backend));
+}
+
+fn backend() -> Rc<dyn Backend> {
+    BACKEND.with(|backend| backend.borrow().clone())
+}
+
+/// Ids only tell apart sockets and listeners, so they do not have to be per thread
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+fn next_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+type Listener = Rc<dyn Fn(WsEvent)>;
+
+/// The socket to a URL, shared by every subscription to it
+struct Connection {
+    /// Changes with every reconnect, so that events of earlier sockets are ignored
+    id: usize,
+    backoff: Backoff,
+    listeners: Vec<(usize, Listener)>,
+    /// The socket, unless a reconnect is pending
+    socket: Option<Box<dyn Socket>>,
+    open: bool,
+    /// Messages sent before the socket was open
+    queue: Vec<Message>,
+    /// Reconnects that failed in a row
+    attempts: u32,
+    retry: Option<Subscription>,
+    /// Whether the backoff ran out of retries, so the socket stays closed
+    gave_up: bool,
+}
+
+fn start(url: String, backoff: Backoff, listener: Listener) -> Result<Subscription, JsValue> {
+    let listener_id = next_id();
+    let listener = CONNECTIONS.with(|connections| match connections.borrow_mut().get_mut(&url) {
+        Some(connection) => {
+            connection.listeners.push((listener_id, listener));
+            None
+        }
+        None => Some(listener),
+    });
+    // The first subscription to a URL opens its socket, and registers it only once it could
+    if let Some(listener) = listener {
+        let id = next_id();
+        let socket = open(&url, id)?;
+        CONNECTIONS.with(|connections| {
+            connections.borrow_mut().insert(
+                url.clone(),
+                Connection {
+                    id,
+                    backoff,
+                    listeners: vec![(listener_id, listener)],
+                    socket: Some(socket),
+                    open: false,
+                    queue: vec![],
+                    attempts: 0,
+                    retry: None,
+                    gave_up: false,
+                },
+            )
+        });
+    }
+    Ok(Subscription::new(move || stop(&url, listener_id)))
+}
+
+fn stop(url: &str, listener_id: usize) {
+    let connection = CONNECTIONS.with(|connections| {
+        let mut connections = connections.borrow_mut();
+        let connection = connections.get_mut(url)?;
+        connection
+            .listeners
+            .retain(|(other_id, _)| *other_id != listener_id);
+        if connection.listeners.is_empty() {
+            connections.remove(url)
+        } else {
+            None
+        }
+    });
+    // The socket and the pending reconnect go away outside of the registry
+    if let Some(socket) = connection.and_then(|connection| connection.socket) {
+        socket.close();
+    }
+}
+
+/// Opens a socket to `url` whose events are handled as those of connection `id`
+fn open(url: &str, id: usize) -> Result<Box<dyn Socket>, JsValue> {
+    let events_url = url.to_owned();
+    backend().connect(url, Box::new(move |event| handle(&events_url, id, event)))
+}
+
+fn connect(url: &str) -> Result<(), JsValue> {
+    let id = next_id();
+    let found = CONNECTIONS.with(|connections| {
+        let mut connections = connections.borrow_mut();
+        match connections.get_mut(url) {
+            Some(connection) => {
+                connection.id = id;
+                true
+            }
+            None => false,
+        }
+    });
+    if !found {
+        return Ok(());
+    }
+    let socket = open(url, id)?;
+    CONNECTIONS.with(|connections| {
+        if let Some(connection) = connections.borrow_mut().get_mut(url) {
+            connection.socket = Some(socket);
+        }
+    });
+    Ok(())
+}
+
+fn handle(url: &str, id: usize, event: WsEvent) {
+    let mut closed = false;
+    let mut closed_socket = None;
+    let listeners = CONNECTIONS.with(|connections| {
+        let mut connections = connections.borrow_mut();
+        let connection = match connections.get_mut(url) {
+            Some(connection) if connection.id == id => connection,
+            _ => return vec![],
+        };
+        match &event {
+            WsEvent::Open => {
+                connection.open = true;
+                connection.attempts = 0;
+                if let Some(socket) = &connection.socket {
+                    for message in connection.queue.drain(..) {
+                        if let Err(error) = socket.send(&message) {
+                            log::error!("failed to send a message to {}: {:?}", url, error);
+                        }
+                    }
+                }
+            }
+            WsEvent::Close { .. } => {
+                connection.open = false;
+                closed_socket = connection.socket.take();
+                closed = true;
+            }
+            WsEvent::Message(_) | WsEvent::Error => {}
+        }
+        connection.listeners.clone()
+    });
+    drop(closed_socket);
+    for (_, listener) in listeners {
+        listener(event.clone());
+    }
+
+    if closed {
+        reconnect_later(url);
+    }
+}
+
+/// Counts a failed attempt to connect to `url`, and tries again after the backoff unless it
+/// ran out of retries. Messages that wait for the socket are dropped then.
+fn reconnect_later(url: &str) {
+    let next = CONNECTIONS.with(|connections| {
+        let mut connections = connections.borrow_mut();
+        let connection = connections.get_mut(url)?;
+        let delay = connection.backoff.delay(connection.attempts);
+        connection.attempts += 1;
+        if delay.is_none() {
+            connection.gave_up = true;
+            connection.queue.clear();
+        }
+        Some((connection.id, delay?))
+    });
+    let (id, delay) = match next {
+        Some(next) => next,
+        None => return,
+    };
+    let retry_url = url.to_owned();
+    match backend().schedule(delay, Box::new(move || retry(&retry_url, id))) {
+        Ok(timer) => CONNECTIONS.with(|connections| {
+            if let Some(connection) = connections.borrow_mut().get_mut(url) {
+                connection.retry = Some(timer);
+            }
+        }),
+        Err(error) => log::error!("failed to reconnect to {}: {:?}", url, error),
+    }
+}
+
+fn retry(url: &str, id: usize) {
+    let timer = CONNECTIONS.with(|connections| match connections.borrow_mut().get_mut(url) {
+        Some(connection) if connection.id == id => connection.retry.take(),
+        _ => None,
+    });
+    // The timer has fired, so letting go of it cancels nothing
+    if timer.is_some() {
+        if let Err(error) = connect(url) {
+            log::error!("failed to reconnect to {}: {:?}", url, error);
+            reconnect_later(url);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listen_with(mock: &MockBackend, url: &str, backoff: Backoff) -> Subscription {
+        set_backend(mock.clone());
+        start(url.to_owned(), backoff, Rc::new(|_| {})).unwrap()
+    }
+
+    fn send_text(url: &str, text: &str) {
+        let dispatcher = Dispatcher::new(|_: ()| {});
+        send(url, Message::Text(text.to_owned()))
+            .run(&dispatcher)
+            .unwrap();
+    }
+
+    fn secs(secs: f64) -> Option<Duration> {
+        Some(Duration::from_secs_f64(secs))
+    }
+
+    #[test]
+    fn backoff_grows_by_its_factor_up_to_the_max() {
+        let backoff = Backoff {
+            retries: Some(4),
+            ..Backoff::default()
+        };
+        let delays = (0..5).map(|attempt| backoff.delay(attempt));
+        assert_eq!(
+            delays.collect::<Vec<_>>(),
+            vec![secs(1.0), secs(2.0), secs(4.0), secs(8.0), None]
+        );
+        assert_eq!(Backoff::default().delay(10), secs(30.0));
+        assert_eq!(Backoff::never().delay(0), None);
+    }
+
+    #[test]
+    fn backoff_survives_factors_that_make_no_sense() {
+        for factor in &[-2.0, f64::NAN, f64::INFINITY, f64::MAX] {
+            let backoff = Backoff {
+                factor: *factor,
+                ..Backoff::default()
+            };
+            for attempt in 0..3 {
+                let delay = backoff.delay(attempt).unwrap();
+                assert!(delay <= backoff.max, "{} gave {:?}", factor, delay);
+            }
+        }
+        let backoff = Backoff {
+            initial: Duration::from_secs(0),
+            factor: f64::INFINITY,
+            ..Backoff::default()
+        };
+        assert!(backoff.delay(1).is_some());
+    }
+
+    #[test]
+    fn stops_reconnecting_once_the_backoff_runs_out() {
+        let mock = MockBackend::new();
+        let url = "wss://retries";
+        let backoff = Backoff {
+            retries: Some(2),
+            ..Backoff::default()
+        };
+        let _subscription = listen_with(&mock, url, backoff);
+
+        mock.close(url, 1006, "");
+        mock.advance(Duration::from_secs(1));
+        mock.close(url, 1006, "");
+        mock.advance(Duration::from_secs(2));
+        assert_eq!(mock.connections(url), 3);
+
+        send_text(url, "queued");
+        mock.close(url, 1006, "");
+        mock.advance(Duration::from_secs(60));
+        assert_eq!(mock.connections(url), 3);
+        assert!(!mock.is_connected(url));
+
+        // Nothing waits for a socket that never comes back
+        send_text(url, "dropped");
+        let queued = CONNECTIONS.with(|connections| connections.borrow()[url].queue.len());
+        assert_eq!(queued, 0);
+    }
+
+    #[test]
+    fn keeps_retrying_reconnects_that_fail() {
+        let mock = MockBackend::new();
+        let url = "wss://down";
+        let _subscription = listen_with(&mock, url, Backoff::default());
+
+        mock.set_refusing(url, true);
+        mock.close(url, 1006, "");
+        mock.advance(Duration::from_secs(1 + 2));
+        assert_eq!(mock.connections(url), 1);
+
+        mock.set_refusing(url, false);
+        mock.advance(Duration::from_secs(4));
+        assert_eq!(mock.connections(url), 2);
+        assert!(mock.is_connected(url));
+    }
+
+    #[test]
+    fn a_failed_connect_can_be_started_again() {
+        let mock = MockBackend::new();
+        let url = "wss://refused";
+        set_backend(mock.clone());
+        mock.set_refusing(url, true);
+        assert!(start(url.to_owned(), Backoff::default(), Rc::new(|_| {})).is_err());
+        assert!(CONNECTIONS.with(|connections| connections.borrow().is_empty()));
+
+        mock.set_refusing(url, false);
+        let _subscription = listen_with(&mock, url, Backoff::default());
+        assert!(mock.is_connected(url));
+    }
+
+    #[test]
+    fn teardown_closes_the_socket_and_cancels_reconnects() {
+        let mock = MockBackend::new();
+        let url = "wss://teardown";
+        let first = listen_with(&mock, url, Backoff::default());
+        let second = listen_with(&mock, url, Backoff::default());
+        assert_eq!(mock.connections(url), 1);
+
+        drop(first);
+        assert!(mock.is_connected(url));
+        drop(second);
+        assert!(!mock.is_connected(url));
+
+        let subscription = listen_with(&mock, url, Backoff::default());
+        mock.close(url, 1006, "");
+        drop(subscription);
+        mock.advance(Duration::from_secs(60));
+        assert_eq!(mock.connections(url), 2);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::platform::Subscription;
+use crate::websocket::{Backend, Message, Socket, WsEvent};
+use std::time::Duration;
+use wasm_bindgen::prelude::{Closure, JsValue};
+use wasm_bindgen::JsCast;
+
+/// The backend of the browser, which opens a `WebSocket`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WebSocketBackend;
+
+impl Backend for WebSocketBackend {
+    fn connect(&self, url: &str, events: Box<dyn Fn(WsEvent)>) -> Result<Box<dyn Socket>, JsValue> {
+        let socket = web_sys::WebSocket::new(url)?;
+        socket.set_binary_type(web_sys::BinaryType::Arraybuffer);
+        let events: std::rc::Rc<dyn Fn(WsEvent)> = events.into();
+
+        let open_events = events.clone();
+        let on_open = Closure::wrap(
+            Box::new(move |_: web_sys::Event| open_events(WsEvent::Open))
+                as Box<dyn FnMut(web_sys::Event)>,
+        );
+        let message_events = events.clone();
+        let on_message = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            let data = event.unchecked_ref::<web_sys::MessageEvent>().data();
+            let message = match data.as_string() {
+                Some(text) => Message::Text(text),
+                None => Message::Binary(js_sys::Uint8Array::new(&data).to_vec()),
+            };
+            message_events(WsEvent::Message(message))
+        }) as Box<dyn FnMut(web_sys::Event)>);
+        let close_events = events.clone();
+        let on_close = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            let event = event.unchecked_ref::<web_sys::CloseEvent>();
+            close_events(WsEvent::Close {
+                code: event.code(),
+                reason: event.reason(),
+            })
+        }) as Box<dyn FnMut(web_sys::Event)>);
+        let on_error = Closure::wrap(Box::new(move |_: web_sys::Event| events(WsEvent::Error))
+            as Box<dyn FnMut(web_sys::Event)>);
+
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        Ok(Box::new(WebSocket {
+            socket,
+            _handlers: vec![on_open, on_message, on_close, on_error],
+        }))
+    }
+
+    fn schedule(
+        &self,
+        delay: Duration,
+        callback: Box<dyn FnOnce()>,
+    ) -> Result<Subscription, JsValue> {
+        let window = web_sys::window().expect("no global `window` exists");
+        let callback = Closure::once(callback);
+        let handle = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            callback.as_ref().unchecked_ref(),
+            delay.as_millis() as i32,
+        )?;
+        Ok(Subscription::new(move || {
+            window.clear_timeout_with_handle(handle);
+            drop(callback);
+        }))
+    }
+}
+
+struct WebSocket {
+    socket: web_sys::WebSocket,
+    // The handlers are only called while the socket is around
+    _handlers: Vec<Closure<dyn FnMut(web_sys::Event)>>,
+}
+
+impl Socket for WebSocket {
+    fn send(&self, message: &Message) -> Result<(), JsValue> {
+        match message {
+            Message::Text(text) => self.socket.send_with_str(text),
+            Message::Binary(bytes) => self.socket.send_with_u8_array(bytes),
+        }
+    }
+
+    fn close(&self) {
+        self.socket.set_onclose(None);
+        if let Err(error) = self.socket.close() {
+            log::warn!("failed to close a WebSocket: {:?}", error);
+        }
+    }
+}
+
+impl Drop for WebSocket {
+    fn drop(&mut self) {
+        self.socket.set_onopen(None);
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        self.socket.set_onerror(None);
+    }
+}
@@ -0,0 +1,14 @@
+//! Renders the counter to a static page, with `cargo run -p counter --bin ssg -- --out dist`
+
+use counter::{view, Model, Msg};
+use oak::html::Html;
+use oak::ssg::{self, Site};
+
+fn page(_: &&str) -> (Model, Html<Msg>) {
+    let model = 0;
+    (model, view(&model))
+}
+
+fn main() {
+    ssg::main(Site::new(vec!["/"], page).head("<title>Counter</title>"));
+}
@@ -15,15 +15,14 @@ fn update(msg: &Msg, model: &mut Model) {
     }
 }
 
-fn view(model: &Model) -> Html<Msg> {
-    div(
-        [],
-        [
-            button([on_click(Msg::Increment)], ["+"]),
-            div([], [model]),
-            button([on_click(Msg::Decrement)], ["-"]),
-        ],
-    )
+pub fn view(model: &Model) -> Html<Msg> {
+    html! {
+        <div>
+            <button on_click={Msg::Increment}>"+"</button>
+            <div>{model}</div>
+            <button on_click={Msg::Decrement}>"-"</button>
+        </div>
+    }
 }
 
 #[wasm_bindgen(start)]
@@ -1,19 +1,20 @@
 use oak::prelude::*;
+use oak::time::{self, Duration};
 
 pub type Model = Time;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Msg {
-    Tick,
+    Tick(Time),
 }
 
 fn init() -> (Model, impl Cmd<Msg>) {
-    (Time::now(), platform::None)
+    (Time::default(), time::now(Msg::Tick))
 }
 
 fn update(msg: &Msg, model: &mut Model) -> impl Cmd<Msg> {
     match msg {
-        Msg::Tick => *model = Time::now(),
+        Msg::Tick(time) => *model = *time,
     }
     platform::None
 }
@@ -28,8 +29,8 @@ fn view(model: &Model) -> Html<Msg> {
     )
 }
 
-fn subscriptions(model: &Model) -> impl Sub<Msg> {
-    platform::None
+fn subscriptions(_: &Model) -> impl Sub<Msg> {
+    time::every(Duration::from_secs(1), Msg::Tick)
 }
 
 #[wasm_bindgen]